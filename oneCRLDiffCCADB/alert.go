@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"bytes"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
+	"net/http"
+	"net/url"
+	"os"
+	"time"
+)
+
+// ThresholdHook is told of a category of the diff whose size exceeds its threshold. The diff is
+// given too, so that the hook may describe it as a whole.
+type ThresholdHook func(category string, count int, diff Return)
+
+// thresholds are the sizes, keyed by category name, beyond which a category of each diff is
+// reported to every one of thresholdHooks.
+var (
+	thresholds     = make(map[string]int)
+	thresholdHooks []ThresholdHook
+)
+
+// checkThresholds calls each hook with every category of the diff, in the order of its fields,
+// that holds more entries than its threshold. Categories without a threshold are never reported.
+func checkThresholds(diff Return, thresholds map[string]int, hooks []ThresholdHook) {
+	categories := diff.categories()
+	for _, name := range categoryNames {
+		threshold, ok := thresholds[name]
+		if count := len(*categories[name]); ok && count > threshold {
+			for _, hook := range hooks {
+				hook(name, count, diff)
+			}
+		}
+	}
+}
+
+// webhookAlert is the body that Webhook posts.
+type webhookAlert struct {
+	Category string
+	Count    int
+	Summary  string
+	// The whole of the alert in a line, as Slack's incoming webhooks expect.
+	Text string `json:"text"`
+}
+
+// Webhook returns a hook that posts the category, its count, and the summary of the diff to the
+// target URL as JSON. The post is made in the background, so that a slow webhook does not hold up
+// the diff, and its failure is logged rather than returned. The URL is left out of the log, as
+// webhook URLs tend to embed their credentials.
+func Webhook(target string) ThresholdHook {
+	client := &http.Client{Timeout: 30 * time.Second}
+	return func(category string, count int, diff Return) {
+		summary := diff.Summary()
+		body, err := json.Marshal(webhookAlert{
+			Category: category,
+			Count:    count,
+			Summary:  summary,
+			Text:     fmt.Sprintf("%s has %d entries, more than its threshold: %s", category, count, summary),
+		})
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "the alert for %s could not be encoded: %s\n", category, err)
+			return
+		}
+		go func() {
+			req, err := http.NewRequest(http.MethodPost, target, bytes.NewReader(body))
+			if err != nil {
+				fmt.Fprintf(os.Stderr, "the alert for %s could not be posted: %s\n", category, withoutURL(err))
+				return
+			}
+			req.Header.Set("Content-Type", "application/json")
+			req.Header.Set("User-Agent", fetch.UserAgent)
+			resp, err := client.Do(req)
+			if err != nil {
+				fmt.Fprintf(os.Stderr, "the alert for %s could not be posted: %s\n", category, withoutURL(err))
+				return
+			}
+			resp.Body.Close()
+			if resp.StatusCode/100 != 2 {
+				fmt.Fprintf(os.Stderr, "the alert for %s was answered with %s\n", category, resp.Status)
+			}
+		}()
+	}
+}
+
+// withoutURL strips the URL from an error of the HTTP client.
+func withoutURL(err error) error {
+	var e *url.Error
+	if errors.As(err, &e) {
+		return e.Err
+	}
+	return err
+}
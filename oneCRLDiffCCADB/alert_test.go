@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"encoding/json"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/normalized"
+	"net/http"
+	"net/http/httptest"
+	"testing"
+	"time"
+)
+
+// alertingDiff has three entries added to the CCADB but absent from OneCRL, and one ready to add.
+func alertingDiff() Return {
+	r := NewReturn()
+	for i := 0; i < 3; i++ {
+		r.AddedAndAbsentFromOneCRL = append(r.AddedAndAbsentFromOneCRL, new(normalized.Normalized))
+	}
+	r.ReadyToAddAndAbsentFromOneCRL = append(r.ReadyToAddAndAbsentFromOneCRL, new(normalized.Normalized))
+	return r
+}
+
+func TestCheckThresholds(t *testing.T) {
+	type call struct {
+		category string
+		count    int
+	}
+	calls := make([]call, 0)
+	hook := func(category string, count int, diff Return) {
+		calls = append(calls, call{category, count})
+	}
+	checkThresholds(alertingDiff(), map[string]int{
+		"AddedAndAbsentFromOneCRL":      2,
+		"ReadyToAddAndAbsentFromOneCRL": 1,
+		"NoRevocationStatus":            0,
+	}, []ThresholdHook{hook})
+	// ReadyToAddAndAbsentFromOneCRL is at its threshold, rather than beyond it.
+	if len(calls) != 1 || calls[0] != (call{"AddedAndAbsentFromOneCRL", 3}) {
+		t.Errorf("wanted the hook to be called once for the 3 AddedAndAbsentFromOneCRL, got %v", calls)
+	}
+}
+
+func TestWebhook(t *testing.T) {
+	alerts := make(chan webhookAlert, 1)
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		var alert webhookAlert
+		if err := json.NewDecoder(r.Body).Decode(&alert); err != nil {
+			t.Error(err)
+		}
+		alerts <- alert
+	}))
+	defer server.Close()
+	diff := alertingDiff()
+	checkThresholds(diff, map[string]int{"AddedAndAbsentFromOneCRL": 0}, []ThresholdHook{Webhook(server.URL)})
+	select {
+	case alert := <-alerts:
+		if alert.Category != "AddedAndAbsentFromOneCRL" || alert.Count != 3 || alert.Summary != diff.Summary() {
+			t.Errorf("wanted the 3 AddedAndAbsentFromOneCRL and the summary of the diff, got %+v", alert)
+		}
+	case <-time.After(10 * time.Second):
+		t.Fatal("the webhook was never posted to")
+	}
+}
@@ -5,14 +5,25 @@
 package ccadb
 
 import (
-	"encoding/hex"
+	"bufio"
+	"bytes"
+	"encoding/csv"
+	"errors"
 	"fmt"
 	"github.com/gocarina/gocsv"
-	"math/big"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/serial"
+	"io"
 	"net/http"
+	"sort"
+	"strings"
+	"time"
 )
 
-const report = "https://ccadb.my.salesforce-sites.com/mozilla/PublicIntermediateCertsRevokedWithPEMCSV"
+const Report = "https://ccadb.my.salesforce-sites.com/mozilla/PublicIntermediateCertsRevokedWithPEMCSV"
+
+// RetryPolicy governs how requests for the report that fail for transient reasons are retried.
+var RetryPolicy = fetch.DefaultPolicy.For("CCADB")
 
 const (
 	Added      = "Added to OneCRL"
@@ -20,19 +31,99 @@ const (
 	Expired    = "Cert Expired"
 )
 
+// The names of the report columns that Entry is built from.
+const (
+	IssuerCommonNameColumn       = "Certificate Issuer Common Name"
+	SerialColumn                 = "Certificate Serial Number"
+	FingerprintColumn            = "SHA-256 Fingerprint"
+	RevocationStatusColumn       = "OneCRL Status"
+	IssuerOrganizationNameColumn = "Certificate Issuer Organization"
+	CAOwnerColumn                = "CA Owner"
+	DateOfRevocationColumn       = "Date of Revocation"
+	ValidToColumn                = "Valid To [GMT]"
+)
+
+// The columns without which entries cannot be joined with OneCRL.
+var requiredColumns = []string{IssuerCommonNameColumn, SerialColumn, RevocationStatusColumn, IssuerOrganizationNameColumn}
+
+// Columns maps the column names above onto the names that the report actually uses, for
+// when the CCADB renames a column. Any column that is not mapped is expected under its
+// usual name.
+var Columns = make(map[string]string)
+
 type Entry struct {
 	IssuerCommonName       string `csv:"Certificate Issuer Common Name" json:"issuerCN"`
 	Serial                 string `csv:"Certificate Serial Number" json:"serial"`
 	Fingerprint            string `csv:"SHA-256 Fingerprint" json:"sha_256"`
 	RevocationStatus       string `csv:"OneCRL Status" json:"revocationStatus"`
 	IssuerOrganizationName string `csv:"Certificate Issuer Organization" json:"issuerON"`
+	// The CA responsible for the certificate, if the report says.
+	CAOwner string `csv:"CA Owner" json:"caOwner,omitempty"`
+	// The date on which the certificate was revoked, if the report says, as the report writes it.
+	DateOfRevocation string `csv:"Date of Revocation" json:"dateOfRevocation,omitempty"`
+	// The last day on which the certificate is valid, if the report says, as the report writes it.
+	ValidTo string `csv:"Valid To [GMT]" json:"validTo,omitempty"`
+}
+
+// dateLayouts are the layouts in which the CCADB writes dates. Its reports do not agree on
+// one, some writing 2006.01.02 and others 2006 Jan 02, so both are accepted, as is ISO 8601.
+var dateLayouts = []string{"2006.01.02", "2006 Jan 02", "2006-01-02"}
+
+// parseDate parses a date of the report, in any of dateLayouts, as midnight UTC.
+func parseDate(column, value string) (time.Time, error) {
+	value = strings.TrimSpace(value)
+	if value == "" {
+		return time.Time{}, fmt.Errorf("the %q column is empty", column)
+	}
+	for _, layout := range dateLayouts {
+		if t, err := time.Parse(layout, value); err == nil {
+			return t, nil
+		}
+	}
+	return time.Time{}, fmt.Errorf("the %q column has the unrecognized date %q", column, value)
+}
+
+// RevokedOn parses DateOfRevocation. It is an error for the report not to give a date.
+func (e *Entry) RevokedOn() (time.Time, error) {
+	return parseDate(DateOfRevocationColumn, e.DateOfRevocation)
+}
+
+// ExpiredAt reports whether the certificate had expired by the given time, which is the case from
+// the day after ValidTo. The report gives only the day, so a certificate is valid throughout it.
+// It is an error for the report not to give a date.
+func (e *Entry) ExpiredAt(now time.Time) (bool, error) {
+	validTo, err := parseDate(ValidToColumn, e.ValidTo)
+	if err != nil {
+		return false, err
+	}
+	return !now.Before(validTo.AddDate(0, 0, 1)), nil
 }
 
 // Key constructs a string that is the concatenation of the certificate serial (decoded from hex to an decimal value)
 // the issuer common name, and the issuer organization name. This key is used to join the results of the CCADB
-// with OneCRL.
-func (e *Entry) Key() string {
-	return fmt.Sprintf("%s%s%s", e.decodeSerial(), e.IssuerCommonName, e.IssuerOrganizationName)
+// with OneCRL. It is an error for the serial not to be hex.
+func (e *Entry) Key() (string, error) {
+	s, err := serial.FromHex(e.Serial)
+	if err != nil {
+		return "", &InvalidSerialError{Fingerprint: e.Fingerprint, Serial: e.Serial, Err: err}
+	}
+	return fmt.Sprintf("%s%s%s", s, e.IssuerCommonName, e.IssuerOrganizationName), nil
+}
+
+// InvalidSerialError is returned for a row of the report whose serial is not hex, and so
+// cannot be joined with OneCRL.
+type InvalidSerialError struct {
+	Fingerprint string
+	Serial      string
+	Err         error
+}
+
+func (e *InvalidSerialError) Error() string {
+	return fmt.Sprintf("the CCADB report has a row (SHA-256 fingerprint %q) whose serial %q is not hex: %v", e.Fingerprint, e.Serial, e.Err)
+}
+
+func (e *InvalidSerialError) Unwrap() error {
+	return e.Err
 }
 
 // Retrieve downloads the CCADB report located at
@@ -42,26 +133,176 @@ func (e *Entry) Key() string {
 // The "key" in this case is the string concatenation of the decimal value of the certificate serial number,
 // the issuer common name, and the issuer organization name.
 func Retrieve() (map[string]*Entry, error) {
-	result := make(map[string]*Entry, 0)
-	resp, err := http.DefaultClient.Get(report)
+	return RetrieveFrom(Report)
+}
+
+// RetrieveFrom is Retrieve against an arbitrary location of the CCADB report.
+func RetrieveFrom(url string) (map[string]*Entry, error) {
+	i, err := RetrieveIndex(url)
+	if err != nil {
+		return make(map[string]*Entry, 0), err
+	}
+	return i.Entries, nil
+}
+
+// Index is the CCADB report mapped by "key", along with the rows that were set aside
+// because another row of the same certificate was preferred to them.
+type Index struct {
+	Entries map[string]*Entry
+	// Every key with more than one row, in the order of their keys.
+	Duplicates []Duplicate
+}
+
+// Duplicate is the several rows of the report for the same certificate, of which only
+// Kept is joined with OneCRL.
+type Duplicate struct {
+	Key       string
+	Kept      *Entry
+	Discarded []*Entry
+}
+
+// Fingerprints returns the SHA-256 fingerprints of the discarded rows.
+func (d Duplicate) Fingerprints() []string {
+	fingerprints := make([]string, len(d.Discarded))
+	for i, e := range d.Discarded {
+		fingerprints[i] = e.Fingerprint
+	}
+	return fingerprints
+}
+
+// RetrieveIndex is RetrieveFrom, also returning the rows that were discarded.
+func RetrieveIndex(url string) (*Index, error) {
+	req, err := http.NewRequest(http.MethodGet, url, nil)
 	if err != nil {
-		return result, err
+		return nil, err
+	}
+	resp, err := RetryPolicy.Do(req)
+	if err != nil {
+		return nil, err
 	}
 	defer resp.Body.Close()
-	var e []*Entry
-	if err := gocsv.Unmarshal(resp.Body, &e); err != nil {
-		return result, err
+	if resp.StatusCode != http.StatusOK {
+		return nil, fmt.Errorf("unexpected status %d when retrieving the CCADB report from %s", resp.StatusCode, url)
 	}
-	for _, cert := range e {
-		result[cert.Key()] = cert
+	e, err := parse(resp.Body)
+	if err != nil {
+		return nil, RetryPolicy.Wrap(url, err)
+	}
+	return index(e)
+}
+
+// statusRank orders the revocation statuses from the most to the least definitive.
+var statusRank = map[string]int{Added: 3, ReadyToAdd: 2, Expired: 1}
+
+// index maps the entries by their "key". The report may have several rows for the same
+// certificate, in which case one is chosen regardless of the order of the rows: the row with
+// the most definitive revocation status ("Added to OneCRL", then "Ready to Add", then "Cert
+// Expired", then any other), and of those the row with the lowest SHA-256 fingerprint. The
+// other rows are recorded as duplicates, in the order of the report.
+func index(entries []*Entry) (*Index, error) {
+	rows := make(map[string][]*Entry, len(entries))
+	result := &Index{Entries: make(map[string]*Entry, len(entries)), Duplicates: make([]Duplicate, 0)}
+	for _, cert := range entries {
+		key, err := cert.Key()
+		if err != nil {
+			return nil, err
+		}
+		rows[key] = append(rows[key], cert)
+		if existing, ok := result.Entries[key]; !ok || cert.preferredTo(existing) {
+			result.Entries[key] = cert
+		}
+	}
+	for key, certs := range rows {
+		if len(certs) < 2 {
+			continue
+		}
+		d := Duplicate{Key: key, Kept: result.Entries[key], Discarded: make([]*Entry, 0, len(certs)-1)}
+		for _, cert := range certs {
+			if cert != d.Kept {
+				d.Discarded = append(d.Discarded, cert)
+			}
+		}
+		result.Duplicates = append(result.Duplicates, d)
+	}
+	sort.Slice(result.Duplicates, func(i, j int) bool {
+		return result.Duplicates[i].Key < result.Duplicates[j].Key
+	})
+	return result, nil
+}
+
+func (e *Entry) preferredTo(other *Entry) bool {
+	rank, otherRank := statusRank[strings.TrimSpace(e.RevocationStatus)], statusRank[strings.TrimSpace(other.RevocationStatus)]
+	if rank != otherRank {
+		return rank > otherRank
 	}
-	return result, err
+	return e.Fingerprint < other.Fingerprint
 }
 
-func (e *Entry) decodeSerial() string {
-	s, err := hex.DecodeString(e.Serial)
+// Reachable reports whether the report at the given location can be retrieved, without reading it.
+func Reachable(url string) error {
+	req, err := http.NewRequest(http.MethodGet, url, nil)
 	if err != nil {
-		panic(err)
+		return err
+	}
+	resp, err := RetryPolicy.Do(req)
+	if err != nil {
+		return err
+	}
+	resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return fmt.Errorf("unexpected status %d when retrieving the CCADB report from %s", resp.StatusCode, url)
+	}
+	return nil
+}
+
+// The UTF-8 byte order mark that some tools prefix their CSV exports with.
+var bom = []byte{0xEF, 0xBB, 0xBF}
+
+// parse reads the entries of the report, renaming its columns according to Columns.
+func parse(r io.Reader) ([]*Entry, error) {
+	buffered := bufio.NewReader(r)
+	// Left in place, a byte order mark would become part of the name of the first column.
+	if prefix, err := buffered.Peek(len(bom)); err == nil && bytes.Equal(prefix, bom) {
+		buffered.Discard(len(bom))
+	}
+	reader := csv.NewReader(buffered)
+	reader.FieldsPerRecord = -1
+	rows, err := reader.ReadAll()
+	if err != nil {
+		return nil, err
+	}
+	if len(rows) == 0 {
+		return nil, errors.New("the CCADB report is empty")
+	}
+	renamed := make(map[string]string, len(Columns))
+	for column, actual := range Columns {
+		renamed[actual] = column
+	}
+	available := make([]string, len(rows[0]))
+	present := make(map[string]bool, len(rows[0]))
+	for i, actual := range rows[0] {
+		available[i] = fmt.Sprintf("%q", actual)
+		if column, ok := renamed[actual]; ok {
+			rows[0][i] = column
+		}
+		present[rows[0][i]] = true
+	}
+	for _, column := range requiredColumns {
+		if !present[column] {
+			actual := column
+			if a, ok := Columns[column]; ok {
+				actual = a
+			}
+			return nil, fmt.Errorf("the CCADB report has no %q column, the available columns are %s", actual, strings.Join(available, ", "))
+		}
+	}
+	buf := new(bytes.Buffer)
+	if err := csv.NewWriter(buf).WriteAll(rows); err != nil {
+		return nil, err
+	}
+	var e []*Entry
+	if err := gocsv.Unmarshal(buf, &e); err != nil {
+		return nil, err
 	}
-	return big.NewInt(0).SetBytes(s).String()
+	return e, nil
 }
@@ -5,7 +5,10 @@
 package ccadb
 
 import (
+	"errors"
+	"strings"
 	"testing"
+	"time"
 )
 
 func TestGet(t *testing.T) {
@@ -15,3 +18,149 @@ func TestGet(t *testing.T) {
 	}
 	t.Log(certs)
 }
+
+func TestKeyIgnoresDERSignByte(t *testing.T) {
+	padded := &Entry{Serial: "00FF01", IssuerCommonName: "CN", IssuerOrganizationName: "O"}
+	unpadded := &Entry{Serial: "FF01", IssuerCommonName: "CN", IssuerOrganizationName: "O"}
+	paddedKey, err := padded.Key()
+	if err != nil {
+		t.Fatal(err)
+	}
+	unpaddedKey, err := unpadded.Key()
+	if err != nil {
+		t.Fatal(err)
+	}
+	if paddedKey != unpaddedKey {
+		t.Errorf("wanted %s, got %s", unpaddedKey, paddedKey)
+	}
+}
+
+const renamedCSV = `"Issuer CN","Issuer O","Serial","OneCRL Status"
+"Example Intermediate CA","Example Org","0102030405","Added to OneCRL"
+`
+
+func TestParseRenamedColumns(t *testing.T) {
+	defer func() { Columns = make(map[string]string) }()
+	Columns = map[string]string{
+		IssuerCommonNameColumn:       "Issuer CN",
+		IssuerOrganizationNameColumn: "Issuer O",
+		SerialColumn:                 "Serial",
+	}
+	e, err := parse(strings.NewReader(renamedCSV))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(e) != 1 {
+		t.Fatalf("wanted 1 entry, got %d", len(e))
+	}
+	if e[0].IssuerCommonName != "Example Intermediate CA" || e[0].IssuerOrganizationName != "Example Org" || e[0].Serial != "0102030405" {
+		t.Errorf("columns were not remapped, got %+v", e[0])
+	}
+}
+
+func TestParseMissingColumn(t *testing.T) {
+	_, err := parse(strings.NewReader(renamedCSV))
+	if err == nil {
+		t.Fatal("expected an error for a report without the expected columns")
+	}
+	if !strings.Contains(err.Error(), `"Issuer CN"`) {
+		t.Errorf("wanted the available columns to be listed, got %s", err)
+	}
+}
+
+func TestParseStripsByteOrderMark(t *testing.T) {
+	report := "\xEF\xBB\xBF" + `"Certificate Issuer Common Name","Certificate Issuer Organization","Certificate Serial Number","OneCRL Status"
+"Example Intermediate CA","Example Org","0102030405","Added to OneCRL"
+`
+	e, err := parse(strings.NewReader(report))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(e) != 1 || e[0].IssuerCommonName != "Example Intermediate CA" {
+		t.Errorf("wanted the first column to parse, got %+v", e)
+	}
+}
+
+func TestIndexPrefersDefinitiveRows(t *testing.T) {
+	row := func(status, fingerprint string) *Entry {
+		return &Entry{Serial: "0102030405", IssuerCommonName: "CN", IssuerOrganizationName: "O", RevocationStatus: status, Fingerprint: fingerprint}
+	}
+	rows := []*Entry{row("", "AA"), row(ReadyToAdd, "CC"), row(Added, "DD"), row(Added, "BB"), row(Expired, "00")}
+	// The choice must not depend upon the order of the report.
+	for _, order := range [][]*Entry{rows, {rows[4], rows[3], rows[2], rows[1], rows[0]}} {
+		result, err := index(order)
+		if err != nil {
+			t.Fatal(err)
+		}
+		if len(result.Entries) != 1 {
+			t.Fatalf("wanted the rows to share a key, got %d", len(result.Entries))
+		}
+		for _, e := range result.Entries {
+			if e.RevocationStatus != Added || e.Fingerprint != "BB" {
+				t.Errorf("wanted the %q row with the lowest fingerprint, got %+v", Added, e)
+			}
+		}
+		if len(result.Duplicates) != 1 {
+			t.Fatalf("wanted 1 duplicate, got %d", len(result.Duplicates))
+		}
+		if d := result.Duplicates[0]; d.Kept.Fingerprint != "BB" || len(d.Discarded) != 4 {
+			t.Errorf("wanted the 4 other rows to be discarded in favor of BB, got %s kept and %v discarded", d.Kept.Fingerprint, d.Fingerprints())
+		}
+	}
+}
+
+func TestIndexRejectsNonHexSerial(t *testing.T) {
+	rows := []*Entry{
+		{Serial: "0102030405", IssuerCommonName: "CN", IssuerOrganizationName: "O", Fingerprint: "AA"},
+		{Serial: "not hex", IssuerCommonName: "CN", IssuerOrganizationName: "O", Fingerprint: "BB"},
+	}
+	_, err := index(rows)
+	var invalid *InvalidSerialError
+	if !errors.As(err, &invalid) {
+		t.Fatalf("wanted an InvalidSerialError, got %v", err)
+	}
+	if invalid.Fingerprint != "BB" || invalid.Serial != "not hex" {
+		t.Errorf("wanted the offending row to be named, got %+v", invalid)
+	}
+}
+
+func TestRevokedOn(t *testing.T) {
+	want := time.Date(2019, time.March, 26, 0, 0, 0, 0, time.UTC)
+	for _, date := range []string{"2019.03.26", "2019 Mar 26", "2019-03-26", " 2019.03.26 "} {
+		got, err := (&Entry{DateOfRevocation: date}).RevokedOn()
+		if err != nil {
+			t.Errorf("%q: %s", date, err)
+		} else if !got.Equal(want) {
+			t.Errorf("%q: wanted %s, got %s", date, want, got)
+		}
+	}
+	for _, date := range []string{"", "26/03/2019"} {
+		if _, err := (&Entry{DateOfRevocation: date}).RevokedOn(); err == nil {
+			t.Errorf("%q: expected an error", date)
+		}
+	}
+}
+
+func TestExpiredAt(t *testing.T) {
+	report := `"Certificate Issuer Common Name","Certificate Issuer Organization","Certificate Serial Number","OneCRL Status","Valid To [GMT]"
+"Example Intermediate CA","Example Org","0102030405","Cert Expired","2019 Mar 26"
+`
+	e, err := parse(strings.NewReader(report))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(e) != 1 || e[0].ValidTo != "2019 Mar 26" {
+		t.Fatalf("wanted the Valid To [GMT] column to parse, got %+v", e)
+	}
+	for now, want := range map[time.Time]bool{
+		time.Date(2019, time.March, 26, 23, 59, 59, 0, time.UTC): false,
+		time.Date(2019, time.March, 27, 0, 0, 0, 0, time.UTC):    true,
+	} {
+		if expired, err := e[0].ExpiredAt(now); err != nil || expired != want {
+			t.Errorf("at %s: wanted expired to be %v, got %v (%v)", now, want, expired, err)
+		}
+	}
+	if _, err := (&Entry{}).ExpiredAt(time.Now()); err == nil {
+		t.Error("expected an error for a row without a Valid To date")
+	}
+}
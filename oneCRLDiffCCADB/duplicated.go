@@ -0,0 +1,114 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
+	"sort"
+	"strconv"
+)
+
+// duplicatedSerial is an issuer and serial number that more than one row of the CCADB report,
+// or more than one OneCRL record, bears. A serial number is unique to its issuer, so this may
+// mean that one of the rows or records is corrupt.
+type duplicatedSerial struct {
+	Key string
+	// Every row of the report that bears the issuer and serial number, the row that was joined first.
+	CCADB []*ccadb.Entry
+	// Every record of the collection that bears the issuer and serial number, in the order of Kinto.
+	OneCRL []*oneCRL.OneCRLIntermediate
+	// The fields whose values differ between the rows of the report, or between the records of
+	// the collection, prefixed by the name of the source.
+	Differing []string
+}
+
+// The fields of a CCADB row that are compared. The issuer names are left out, being part of the key.
+var ccadbFields = []struct {
+	name  string
+	value func(*ccadb.Entry) string
+}{
+	{"Serial", func(e *ccadb.Entry) string { return e.Serial }},
+	{"Fingerprint", func(e *ccadb.Entry) string { return e.Fingerprint }},
+	{"RevocationStatus", func(e *ccadb.Entry) string { return e.RevocationStatus }},
+	{"CAOwner", func(e *ccadb.Entry) string { return e.CAOwner }},
+}
+
+// The fields of a OneCRL record that are compared. The id and last modified time
+// differ between any two records, so say nothing of whether they agree.
+var oneCRLFields = []struct {
+	name  string
+	value func(*oneCRL.OneCRLIntermediate) string
+}{
+	{"SerialNumber", func(o *oneCRL.OneCRLIntermediate) string { return o.SerialNumber }},
+	{"Enabled", func(o *oneCRL.OneCRLIntermediate) string { return strconv.FormatBool(o.Enabled) }},
+	{"Details.Bug", func(o *oneCRL.OneCRLIntermediate) string { return o.Details.Bug }},
+	{"Details.Who", func(o *oneCRL.OneCRLIntermediate) string { return o.Details.Who }},
+	{"Details.Why", func(o *oneCRL.OneCRLIntermediate) string { return o.Details.Why }},
+	{"Details.Name", func(o *oneCRL.OneCRLIntermediate) string { return o.Details.Name }},
+	{"Details.Created", func(o *oneCRL.OneCRLIntermediate) string { return o.Details.Created }},
+}
+
+// duplicatedSerials finds the issuers and serial numbers that appear more than once in the CCADB
+// report or in the OneCRL collection, ordered by key. Each source contributes only one row or
+// record of an issuer and serial number to the join, so this is a pass over the sources as they
+// were before the join, rather than over its result. A certificate with one row in the report
+// and one record in the collection is merely joined, and is not reported.
+func duplicatedSerials(report *ccadb.Index, collection *oneCRL.Collection) []duplicatedSerial {
+	rows := make(map[string][]*ccadb.Entry, len(report.Entries))
+	for key, entry := range report.Entries {
+		rows[key] = []*ccadb.Entry{entry}
+	}
+	for _, d := range report.Duplicates {
+		rows[d.Key] = append(rows[d.Key], d.Discarded...)
+	}
+	records := make(map[string][]*oneCRL.OneCRLIntermediate)
+	for _, cert := range collection.Records {
+		if cert.IsIssuerSerial() {
+			records[cert.Key()] = append(records[cert.Key()], cert)
+		}
+	}
+	keys := make(map[string]bool)
+	for key := range rows {
+		keys[key] = true
+	}
+	for key := range records {
+		keys[key] = true
+	}
+	duplicated := make([]duplicatedSerial, 0)
+	for key := range keys {
+		if len(rows[key]) < 2 && len(records[key]) < 2 {
+			continue
+		}
+		d := duplicatedSerial{Key: key, CCADB: rows[key], OneCRL: records[key], Differing: make([]string, 0)}
+		if d.CCADB == nil {
+			d.CCADB = make([]*ccadb.Entry, 0)
+		}
+		if d.OneCRL == nil {
+			d.OneCRL = make([]*oneCRL.OneCRLIntermediate, 0)
+		}
+		for _, field := range ccadbFields {
+			for i := 1; i < len(d.CCADB); i++ {
+				if field.value(d.CCADB[i]) != field.value(d.CCADB[0]) {
+					d.Differing = append(d.Differing, "CCADB."+field.name)
+					break
+				}
+			}
+		}
+		for _, field := range oneCRLFields {
+			for i := 1; i < len(d.OneCRL); i++ {
+				if field.value(d.OneCRL[i]) != field.value(d.OneCRL[0]) {
+					d.Differing = append(d.Differing, "OneCRL."+field.name)
+					break
+				}
+			}
+		}
+		duplicated = append(duplicated, d)
+	}
+	sort.Slice(duplicated, func(i, j int) bool {
+		return duplicated[i].Key < duplicated[j].Key
+	})
+	return duplicated
+}
@@ -0,0 +1,52 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"net/http"
+	"net/http/httptest"
+	"reflect"
+	"testing"
+)
+
+func TestDuplicatedSerials(t *testing.T) {
+	// The first serial has a second row and a second record, each of which disagrees with the first.
+	report := ccadbCSV + `"Other Owner","Example Intermediate CA","Example Org","0102030405","CC","Ready to Add"` + "\n"
+	records := `{
+	"data": [
+		{"enabled": true, "issuerName": "` + issuerName + `", "serialNumber": "AQIDBAU=", "id": "first", "details": {"bug": "https://bugzilla.mozilla.org/1"}},
+		{"enabled": true, "issuerName": "` + issuerName + `", "serialNumber": "AQIDBAU=", "id": "second", "details": {"bug": "https://bugzilla.mozilla.org/2"}},
+		{"enabled": true, "issuerName": "` + issuerName + `", "serialNumber": "CgsM", "id": "once"}
+	]
+}`
+	mux := http.NewServeMux()
+	mux.HandleFunc("/ccadb", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(report))
+	})
+	mux.HandleFunc("/onecrl", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(records))
+	})
+	server := httptest.NewServer(mux)
+	defer server.Close()
+	_, index, collection, err := buildFrom(server.URL+"/ccadb", server.URL+"/onecrl", buildOptions{NoCache: true})
+	if err != nil {
+		t.Fatal(err)
+	}
+	duplicated := duplicatedSerials(index, collection)
+	if len(duplicated) != 1 {
+		t.Fatalf("wanted 1 duplicated issuer and serial, got %d", len(duplicated))
+	}
+	d := duplicated[0]
+	if len(d.CCADB) != 2 || d.CCADB[0].Fingerprint != "AA" {
+		t.Errorf("wanted both rows with the joined row first, got %+v", d.CCADB)
+	}
+	if len(d.OneCRL) != 2 || d.OneCRL[0].Id != "first" {
+		t.Errorf("wanted both records in the order of Kinto, got %+v", d.OneCRL)
+	}
+	want := []string{"CCADB.Fingerprint", "CCADB.RevocationStatus", "CCADB.CAOwner", "OneCRL.Details.Bug"}
+	if !reflect.DeepEqual(d.Differing, want) {
+		t.Errorf("wanted %v to differ, got %v", want, d.Differing)
+	}
+}
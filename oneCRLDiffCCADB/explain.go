@@ -0,0 +1,163 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/serial"
+	"net/http"
+	"strings"
+)
+
+// explanation is the response of explainEndpoint.
+type explanation struct {
+	// The serial number as it was given, and as the decimal on which the sources are joined.
+	Serial        string
+	DecimalSerial string
+	// The entries of each source that bear the serial number.
+	CCADB  []ccadbMatch
+	OneCRL []oneCRLMatch
+	// Why the entries were, or were not, joined.
+	Notes []string
+}
+
+type ccadbMatch struct {
+	Entry *ccadb.Entry
+	Key   string
+}
+
+type oneCRLMatch struct {
+	// The record as Kinto has it, its issuer name still base64.
+	Record *oneCRL.OneCRLIntermediate
+	// The names that are parsed from the issuer name, and every attribute of it.
+	IssuerCommonName       string
+	IssuerOrganizationName string
+	IssuerAttributes       []oneCRL.Attribute
+	Key                    string
+}
+
+// explain finds the entries of each source that bear the given hex serial number, and notes
+// why those entries were or were not joined, for when an entry of the diff is unexpected.
+func explain(hexSerial string, c map[string]*ccadb.Entry, o *oneCRL.Collection) (explanation, error) {
+	decimal, err := serial.FromHex(hexSerial)
+	if err != nil {
+		return explanation{}, fmt.Errorf("%q is not a hex serial number: %s", hexSerial, err)
+	}
+	e := explanation{
+		Serial:        hexSerial,
+		DecimalSerial: decimal,
+		CCADB:         make([]ccadbMatch, 0),
+		OneCRL:        make([]oneCRLMatch, 0),
+		Notes:         make([]string, 0),
+	}
+	for key, entry := range c {
+		if s, err := serial.FromHex(entry.Serial); err == nil && s == decimal {
+			e.CCADB = append(e.CCADB, ccadbMatch{Entry: entry, Key: key})
+		}
+	}
+	for _, record := range o.Records {
+		if !record.IsIssuerSerial() {
+			continue
+		}
+		if s, err := serial.FromBase64(record.SerialNumber); err == nil && s == decimal {
+			cn, org := record.IssuerName.Key()
+			e.OneCRL = append(e.OneCRL, oneCRLMatch{
+				Record:                 record,
+				IssuerCommonName:       cn,
+				IssuerOrganizationName: org,
+				IssuerAttributes:       record.IssuerName.Attributes(),
+				Key:                    record.Key(),
+			})
+		}
+	}
+	note := func(format string, args ...interface{}) {
+		e.Notes = append(e.Notes, fmt.Sprintf(format, args...))
+	}
+	switch {
+	case len(e.CCADB) == 0 && len(e.OneCRL) == 0:
+		note("neither the CCADB nor OneCRL has an entry with this serial number")
+	case len(e.CCADB) == 0:
+		note("the CCADB has no entry with this serial number")
+	case len(e.OneCRL) == 0:
+		note("OneCRL has no record with this serial number")
+	}
+	for _, r := range e.OneCRL {
+		if !r.Record.Enabled {
+			note("OneCRL record %s is disabled, so is left out of the diff unless include_disabled is set", r.Record.Id)
+		}
+	}
+	for _, m := range e.CCADB {
+		for _, r := range e.OneCRL {
+			if m.Key == r.Key {
+				note("the CCADB entry and OneCRL record %s are joined, as their keys match", r.Record.Id)
+				continue
+			}
+			compare := func(field, ccadbValue, oneCRLValue string) {
+				switch {
+				case ccadbValue == oneCRLValue:
+				case strings.EqualFold(strings.TrimSpace(ccadbValue), strings.TrimSpace(oneCRLValue)):
+					note("the issuer %s of OneCRL record %s differs from the CCADB only in case or surrounding whitespace (%q in the CCADB, %q in OneCRL), which prevents the join",
+						field, r.Record.Id, ccadbValue, oneCRLValue)
+				default:
+					note("the issuer %s of OneCRL record %s differs from the CCADB (%q in the CCADB, %q in OneCRL), which prevents the join",
+						field, r.Record.Id, ccadbValue, oneCRLValue)
+				}
+			}
+			compare("common name", m.Entry.IssuerCommonName, r.IssuerCommonName)
+			compare("organization", m.Entry.IssuerOrganizationName, r.IssuerOrganizationName)
+		}
+	}
+	return e, nil
+}
+
+// explainEndpoint explains the entries of each source that bear the hex "serial" query parameter.
+func explainEndpoint(w http.ResponseWriter, r *http.Request) {
+	oneCRLURL, custom, err := oneCRLEndpoint(r)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	hexSerial := r.URL.Query().Get("serial")
+	if hexSerial == "" {
+		w.WriteHeader(400)
+		w.Write([]byte(`the "serial" query parameter is required`))
+		return
+	}
+	if _, err := serial.FromHex(hexSerial); err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(fmt.Sprintf("%q is not a hex serial number: %s", hexSerial, err)))
+		return
+	}
+	c, err := ccadb.RetrieveFrom(ccadbReport)
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	collection, err := retrieve(oneCRLURL, custom)
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	e, err := explain(hexSerial, c, collection)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	j, err := json.MarshalIndent(e, "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"encoding/json"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+)
+
+func explainFixtures(t *testing.T) (map[string]*ccadb.Entry, *oneCRL.Collection) {
+	c := make(map[string]*ccadb.Entry)
+	for _, e := range []*ccadb.Entry{
+		{IssuerCommonName: "Example Intermediate CA", IssuerOrganizationName: "Example Org", Serial: "0A0B0C", RevocationStatus: ccadb.ReadyToAdd},
+		{IssuerCommonName: "example intermediate ca ", IssuerOrganizationName: "Example Org", Serial: "01:02:03:04:05", RevocationStatus: ccadb.Added},
+	} {
+		key, err := e.Key()
+		if err != nil {
+			t.Fatal(err)
+		}
+		c[key] = e
+	}
+	o := new(oneCRL.Collection)
+	records := `[{"enabled": true, "issuerName": "` + issuerName + `", "serialNumber": "AQIDBAU=", "id": "case-mismatch"}]`
+	if err := json.Unmarshal([]byte(records), &o.Records); err != nil {
+		t.Fatal(err)
+	}
+	return c, o
+}
+
+func TestExplainPresentInOneSource(t *testing.T) {
+	c, o := explainFixtures(t)
+	e, err := explain("0a0b0c", c, o)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if e.DecimalSerial != "658188" {
+		t.Errorf("wanted the decimal serial 658188, got %s", e.DecimalSerial)
+	}
+	if len(e.CCADB) != 1 || e.CCADB[0].Entry.Serial != "0A0B0C" {
+		t.Errorf("wanted the CCADB entry, got %+v", e.CCADB)
+	}
+	if len(e.OneCRL) != 0 {
+		t.Errorf("wanted no OneCRL records, got %+v", e.OneCRL)
+	}
+	if len(e.Notes) != 1 || e.Notes[0] != "OneCRL has no record with this serial number" {
+		t.Errorf("wanted a note that OneCRL has no such record, got %q", e.Notes)
+	}
+}
+
+func TestExplainEncodingMismatch(t *testing.T) {
+	c, o := explainFixtures(t)
+	e, err := explain("0102030405", c, o)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(e.CCADB) != 1 || len(e.OneCRL) != 1 {
+		t.Fatalf("wanted an entry in each source, got %+v and %+v", e.CCADB, e.OneCRL)
+	}
+	if e.OneCRL[0].IssuerCommonName != "Example Intermediate CA" {
+		t.Errorf("wanted the parsed issuer common name, got %q", e.OneCRL[0].IssuerCommonName)
+	}
+	if len(e.Notes) != 1 || !strings.Contains(e.Notes[0], "only in case or surrounding whitespace") {
+		t.Errorf("wanted a note that the common names differ only in case, got %q", e.Notes)
+	}
+	if _, err := explain("not hex", c, o); err == nil {
+		t.Error("expected an error for a serial that is not hex")
+	}
+}
+
+func TestExplainEndpointRejectsNonHexSerialBeforeFetching(t *testing.T) {
+	defer func(report string) { ccadbReport = report }(ccadbReport)
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		t.Errorf("wanted no source to be retrieved, got a request for %s", r.URL)
+	}))
+	defer server.Close()
+	ccadbReport = server.URL
+	w := httptest.NewRecorder()
+	explainEndpoint(w, httptest.NewRequest(http.MethodGet, "/explain?serial=not+hex", nil))
+	if w.Code != http.StatusBadRequest {
+		t.Errorf("wanted status %d, got %d", http.StatusBadRequest, w.Code)
+	}
+}
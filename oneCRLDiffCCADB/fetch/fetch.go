@@ -0,0 +1,238 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Package fetch is the HTTP layer shared by the upstream sources, retrying requests
+// that fail for transient reasons.
+package fetch
+
+import (
+	"errors"
+	"fmt"
+	"io"
+	"math/rand"
+	"net"
+	"net/http"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+)
+
+// Policy describes how many times, and how patiently, a request is attempted.
+type Policy struct {
+	// The name of the source that requests are made to, for use in errors.
+	Source string
+	// The total number of attempts, including the first.
+	Attempts int
+	// The delay before the second attempt, which is doubled for each subsequent attempt.
+	Backoff time.Duration
+	// The upper bound of any single delay that is not requested by the server.
+	MaxBackoff time.Duration
+	// The upper bound of the total time spent waiting between attempts, including any that the
+	// server requests with Retry-After. Zero is no limit.
+	MaxWait time.Duration
+	// The time allowed to establish a connection, and to complete an attempt (including
+	// reading its response body). Zero is no limit.
+	ConnectTimeout time.Duration
+	Timeout        time.Duration
+}
+
+var DefaultPolicy = Policy{
+	Attempts:       3,
+	Backoff:        time.Second,
+	MaxBackoff:     30 * time.Second,
+	MaxWait:        2 * time.Minute,
+	ConnectTimeout: 10 * time.Second,
+	Timeout:        2 * time.Minute,
+}
+
+// UserAgent identifies this tool in the User-Agent header of every request that Do sends,
+// so that the operators of the upstream sources can attribute its traffic.
+var UserAgent = "CCADB-Tools/oneCRLDiffCCADB (+https://github.com/mozilla/CCADB-Tools)"
+
+// For returns a copy of the policy for requests to the named source.
+func (p Policy) For(source string) Policy {
+	p.Source = source
+	return p
+}
+
+// Error is returned when every attempt of a request has failed.
+type Error struct {
+	URL string
+	// A description of the outcome of each attempt, in order.
+	Outcomes []string
+}
+
+// TimeoutError is returned when a request to a source does not complete within the limits of its Policy.
+type TimeoutError struct {
+	Source         string
+	URL            string
+	ConnectTimeout time.Duration
+	Timeout        time.Duration
+}
+
+func (e *TimeoutError) Error() string {
+	return fmt.Sprintf("%s timed out retrieving %s (connect timeout %s, request timeout %s)", e.Source, e.URL, e.ConnectTimeout, e.Timeout)
+}
+
+// RateLimitError is returned when a server asks, by way of Retry-After, that a request
+// be retried later than the policy is prepared to wait.
+type RateLimitError struct {
+	URL        string
+	RetryAfter time.Duration
+	MaxWait    time.Duration
+}
+
+func (e *RateLimitError) Error() string {
+	return fmt.Sprintf("%s is rate limiting us and asked that we retry in %s, which exceeds the %s that we are prepared to wait", e.URL, e.RetryAfter, e.MaxWait)
+}
+
+func (e *Error) Error() string {
+	attempts := make([]string, len(e.Outcomes))
+	for i, outcome := range e.Outcomes {
+		attempts[i] = fmt.Sprintf("attempt %d: %s", i+1, outcome)
+	}
+	return fmt.Sprintf("giving up on %s after %d attempts (%s)", e.URL, len(e.Outcomes), strings.Join(attempts, "; "))
+}
+
+// Do sends the request, retrying connection errors, timeouts, 5xx responses, and 429 responses.
+// Any Retry-After header is honored in full, unless doing so would exceed MaxWait, in which case a
+// RateLimitError is returned. Any other response is returned to the caller as is. Should the last
+// attempt that is made, whether for running out of Attempts or of MaxWait, have timed out, a
+// TimeoutError is returned. The request is sent with UserAgent unless it already has one.
+//
+// The request must not have a body.
+func (p Policy) Do(req *http.Request) (*http.Response, error) {
+	if req.Header.Get("User-Agent") == "" {
+		req.Header.Set("User-Agent", UserAgent)
+	}
+	e := &Error{URL: req.URL.String()}
+	delay := p.Backoff
+	var waited time.Duration
+	for attempt := 1; ; attempt++ {
+		resp, err := p.client().Do(req)
+		var wait time.Duration
+		// A source that is still timing out once we give up on it is reported as such.
+		failure := error(e)
+		if isTimeout(err) {
+			failure = p.Wrap(e.URL, err)
+		}
+		switch {
+		case err != nil:
+			e.Outcomes = append(e.Outcomes, err.Error())
+		case resp.StatusCode == http.StatusTooManyRequests:
+			e.Outcomes = append(e.Outcomes, resp.Status)
+			wait = retryAfter(resp)
+			resp.Body.Close()
+		case resp.StatusCode >= 500:
+			e.Outcomes = append(e.Outcomes, resp.Status)
+			resp.Body.Close()
+		default:
+			return resp, nil
+		}
+		if attempt >= p.Attempts {
+			return nil, failure
+		}
+		requested := wait > 0
+		if !requested {
+			wait = jitter(delay)
+			if p.MaxBackoff > 0 && wait > p.MaxBackoff {
+				wait = p.MaxBackoff
+			}
+		}
+		if p.MaxWait > 0 && waited+wait > p.MaxWait {
+			if requested {
+				return nil, &RateLimitError{URL: e.URL, RetryAfter: wait, MaxWait: p.MaxWait}
+			}
+			return nil, failure
+		}
+		time.Sleep(wait)
+		waited += wait
+		delay *= 2
+	}
+}
+
+// Wrap returns a TimeoutError in place of err should err be a timeout, such as when the body
+// of a response that Do returned was still being read when the policy's timeout elapsed.
+func (p Policy) Wrap(url string, err error) error {
+	if !isTimeout(err) {
+		return err
+	}
+	return &TimeoutError{Source: p.Source, URL: url, ConnectTimeout: p.ConnectTimeout, Timeout: p.Timeout}
+}
+
+func isTimeout(err error) bool {
+	var ne net.Error
+	return errors.As(err, &ne) && ne.Timeout()
+}
+
+// The transports used by policies with a connect timeout, keyed by that timeout, so that
+// connections are pooled across requests rather than established anew by each.
+var transports sync.Map
+
+func (p Policy) client() *http.Client {
+	if p.ConnectTimeout == 0 && p.Timeout == 0 {
+		return http.DefaultClient
+	}
+	transport, ok := transports.Load(p.ConnectTimeout)
+	if !ok {
+		t := http.DefaultTransport.(*http.Transport).Clone()
+		if p.ConnectTimeout > 0 {
+			t.DialContext = (&net.Dialer{Timeout: p.ConnectTimeout, KeepAlive: 30 * time.Second}).DialContext
+		}
+		transport, _ = transports.LoadOrStore(p.ConnectTimeout, t)
+	}
+	return &http.Client{Timeout: p.Timeout, Transport: transport.(*http.Transport)}
+}
+
+// retryAfter returns how long the server asked us to wait, or zero if it did not say.
+func retryAfter(resp *http.Response) time.Duration {
+	header := resp.Header.Get("Retry-After")
+	if seconds, err := strconv.Atoi(header); err == nil && seconds > 0 {
+		return time.Duration(seconds) * time.Second
+	}
+	if date, err := http.ParseTime(header); err == nil {
+		return time.Until(date)
+	}
+	return 0
+}
+
+// jitter randomly lengthens the given delay by up to a half so that
+// many clients backing off at once do not retry in lockstep.
+func jitter(delay time.Duration) time.Duration {
+	if delay <= 0 {
+		return 0
+	}
+	return delay + time.Duration(rand.Int63n(int64(delay/2)+1))
+}
+
+// ErrTooLarge is returned by a reader from Limit once its limit has been exceeded.
+var ErrTooLarge = errors.New("the response body exceeds the maximum allowed size")
+
+// Limit returns a reader that reads at most n bytes from r, failing with ErrTooLarge
+// rather than silently truncating should r hold any more than that.
+func Limit(r io.Reader, n int64) io.Reader {
+	return &limitedReader{r: r, n: n}
+}
+
+type limitedReader struct {
+	r io.Reader
+	n int64
+}
+
+func (l *limitedReader) Read(p []byte) (int, error) {
+	if l.n <= 0 {
+		var b [1]byte
+		if n, _ := l.r.Read(b[:]); n > 0 {
+			return 0, ErrTooLarge
+		}
+		return 0, io.EOF
+	}
+	if int64(len(p)) > l.n {
+		p = p[:l.n]
+	}
+	n, err := l.r.Read(p)
+	l.n -= int64(n)
+	return n, err
+}
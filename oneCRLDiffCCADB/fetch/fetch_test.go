@@ -0,0 +1,226 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package fetch
+
+import (
+	"io"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"sync/atomic"
+	"testing"
+	"time"
+)
+
+var quick = Policy{Attempts: 3, Backoff: time.Millisecond, MaxBackoff: 10 * time.Millisecond}
+
+// failing returns a server that responds with the given status to the first n requests
+// and with a 200 thereafter, along with a pointer to the number of requests it has seen.
+func failing(t *testing.T, n int, status int) (*httptest.Server, *int) {
+	requests := 0
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		requests++
+		if requests <= n {
+			w.WriteHeader(status)
+			return
+		}
+		w.Write([]byte("ok"))
+	}))
+	t.Cleanup(server.Close)
+	return server, &requests
+}
+
+func get(t *testing.T, p Policy, url string) (*http.Response, error) {
+	req, err := http.NewRequest(http.MethodGet, url, nil)
+	if err != nil {
+		t.Fatal(err)
+	}
+	return p.Do(req)
+}
+
+func TestRetriesTransientFailures(t *testing.T) {
+	server, requests := failing(t, 2, http.StatusServiceUnavailable)
+	resp, err := get(t, quick, server.URL)
+	if err != nil {
+		t.Fatal(err)
+	}
+	resp.Body.Close()
+	if *requests != 3 {
+		t.Errorf("wanted 3 requests, got %d", *requests)
+	}
+}
+
+func TestGivesUpAfterAttempts(t *testing.T) {
+	server, requests := failing(t, 3, http.StatusBadGateway)
+	_, err := get(t, quick, server.URL)
+	if err == nil {
+		t.Fatal("expected an error after exhausting every attempt")
+	}
+	if *requests != 3 {
+		t.Errorf("wanted 3 requests, got %d", *requests)
+	}
+	if e, ok := err.(*Error); !ok || len(e.Outcomes) != 3 {
+		t.Fatalf("wanted an *Error enumerating 3 attempts, got %v", err)
+	}
+	if !strings.Contains(err.Error(), "attempt 3: 502 Bad Gateway") {
+		t.Errorf("wanted the final outcome in the error, got %s", err)
+	}
+}
+
+func TestDoesNotRetryClientErrors(t *testing.T) {
+	server, requests := failing(t, 1, http.StatusNotFound)
+	resp, err := get(t, quick, server.URL)
+	if err != nil {
+		t.Fatal(err)
+	}
+	resp.Body.Close()
+	if resp.StatusCode != http.StatusNotFound {
+		t.Errorf("wanted the 404 to be returned, got %d", resp.StatusCode)
+	}
+	if *requests != 1 {
+		t.Errorf("wanted 1 request, got %d", *requests)
+	}
+}
+
+func TestRetryAfter(t *testing.T) {
+	resp := &http.Response{Header: http.Header{"Retry-After": []string{"2"}}}
+	if got := retryAfter(resp); got != 2*time.Second {
+		t.Errorf("wanted 2s, got %s", got)
+	}
+	resp.Header.Set("Retry-After", "soon")
+	if got := retryAfter(resp); got != 0 {
+		t.Errorf("wanted 0 for an unparseable Retry-After, got %s", got)
+	}
+}
+
+func TestTimeoutNamesSource(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		time.Sleep(200 * time.Millisecond)
+	}))
+	t.Cleanup(server.Close)
+	p := quick.For("Example")
+	p.Timeout = 20 * time.Millisecond
+	_, err := get(t, p, server.URL)
+	e, ok := err.(*TimeoutError)
+	if !ok {
+		t.Fatalf("wanted a *TimeoutError, got %v", err)
+	}
+	if e.Source != "Example" || e.Timeout != p.Timeout {
+		t.Errorf("wanted the source and its limit in the error, got %+v", e)
+	}
+}
+
+func TestRetriesTimeouts(t *testing.T) {
+	var requests int32
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		if atomic.AddInt32(&requests, 1) == 1 {
+			time.Sleep(200 * time.Millisecond)
+		}
+		w.Write([]byte("ok"))
+	}))
+	t.Cleanup(server.Close)
+	p := quick.For("Example")
+	p.Timeout = 50 * time.Millisecond
+	resp, err := get(t, p, server.URL)
+	if err != nil {
+		t.Fatalf("wanted the second attempt to succeed, got %v", err)
+	}
+	resp.Body.Close()
+	if n := atomic.LoadInt32(&requests); n != 2 {
+		t.Errorf("wanted 2 requests, got %d", n)
+	}
+}
+
+func TestWrapBodyTimeout(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte("partial"))
+		w.(http.Flusher).Flush()
+		time.Sleep(200 * time.Millisecond)
+	}))
+	t.Cleanup(server.Close)
+	p := quick.For("Example")
+	p.Timeout = 50 * time.Millisecond
+	resp, err := get(t, p, server.URL)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer resp.Body.Close()
+	_, err = io.ReadAll(resp.Body)
+	if _, ok := p.Wrap(server.URL, err).(*TimeoutError); !ok {
+		t.Errorf("wanted a *TimeoutError while reading the body, got %v", err)
+	}
+}
+
+func TestHonorsRetryAfter(t *testing.T) {
+	requests := 0
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		requests++
+		if requests == 1 {
+			w.Header().Set("Retry-After", "1")
+			w.WriteHeader(http.StatusTooManyRequests)
+			return
+		}
+		w.Write([]byte("ok"))
+	}))
+	t.Cleanup(server.Close)
+	start := time.Now()
+	resp, err := get(t, quick, server.URL)
+	if err != nil {
+		t.Fatal(err)
+	}
+	resp.Body.Close()
+	if elapsed := time.Since(start); elapsed < time.Second {
+		t.Errorf("wanted the retry to wait at least the requested 1s, waited %s", elapsed)
+	}
+	if requests != 2 {
+		t.Errorf("wanted 2 requests, got %d", requests)
+	}
+}
+
+func TestGivesUpOnExcessiveRetryAfter(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Header().Set("Retry-After", "3600")
+		w.WriteHeader(http.StatusTooManyRequests)
+	}))
+	t.Cleanup(server.Close)
+	p := quick
+	p.MaxWait = time.Minute
+	_, err := get(t, p, server.URL)
+	e, ok := err.(*RateLimitError)
+	if !ok {
+		t.Fatalf("wanted a *RateLimitError, got %v", err)
+	}
+	if e.RetryAfter != time.Hour || e.MaxWait != time.Minute {
+		t.Errorf("wanted the requested and permitted waits in the error, got %+v", e)
+	}
+}
+
+func TestUserAgent(t *testing.T) {
+	server := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(r.UserAgent()))
+	}))
+	defer server.Close()
+	for _, want := range []string{UserAgent, "custom/1.0"} {
+		req, err := http.NewRequest(http.MethodGet, server.URL, nil)
+		if err != nil {
+			t.Fatal(err)
+		}
+		if want != UserAgent {
+			req.Header.Set("User-Agent", want)
+		}
+		resp, err := quick.Do(req)
+		if err != nil {
+			t.Fatal(err)
+		}
+		got, err := io.ReadAll(resp.Body)
+		resp.Body.Close()
+		if err != nil {
+			t.Fatal(err)
+		}
+		if string(got) != want {
+			t.Errorf("wanted User-Agent %q, got %q", want, got)
+		}
+	}
+}
@@ -4,28 +4,74 @@
 
 package main // import "github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB"
 import (
+	"bytes"
+	"context"
 	"encoding/json"
-	//	"errors"
+	"errors"
 	"fmt"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/normalized"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
+	"io"
+	"net"
 	"net/http"
 	"os"
+	"os/signal"
+	"sort"
+	"strconv"
+	"strings"
+	"syscall"
+	"time"
 )
 
-func build() ([]*normalized.Normalized, error) {
+// buildOptions adjust how buildFrom treats OneCRL.
+type buildOptions struct {
+	// Download OneCRL afresh, rather than reusing a previous download that is still current.
+	NoCache bool
+	// Include disabled OneCRL records in the join.
+	IncludeDisabled bool
+	// Tolerate a OneCRL collection none of whose issuer names could be parsed, which
+	// otherwise is an error rather than a silently empty join.
+	AllowUnparsedIssuers bool
+}
+
+// buildFrom joins the CCADB report with OneCRL, also returning the indexed CCADB report and
+// the OneCRL collection that were used. The two sources are retrieved concurrently.
+func buildFrom(ccadbURL, oneCRLURL string, options buildOptions) ([]*normalized.Normalized, *ccadb.Index, *oneCRL.Collection, error) {
 	n := make([]*normalized.Normalized, 0)
-	c, err := ccadb.Retrieve()
-	if err != nil {
-		return n, err
+	var c *ccadb.Index
+	var ccadbErr error
+	done := make(chan struct{})
+	go func() {
+		defer close(done)
+		c, ccadbErr = ccadb.RetrieveIndex(ccadbURL)
+	}()
+	var o *oneCRL.Collection
+	var err error
+	if options.NoCache {
+		o, err = oneCRL.RetrieveCollection(oneCRLURL)
+	} else {
+		o, err = oneCRL.RetrieveCachedCollection(oneCRLURL)
+	}
+	<-done
+	if ccadbErr != nil {
+		return n, nil, nil, ccadbErr
 	}
-	o, err := oneCRL.Retrieve()
 	if err != nil {
-		return n, err
+		return n, nil, nil, err
+	}
+	if !options.AllowUnparsedIssuers {
+		if err := o.CheckIssuers(); err != nil {
+			return n, nil, nil, err
+		}
+	}
+	records := o.EnabledIntermediates()
+	if options.IncludeDisabled {
+		records = o.Intermediates()
 	}
-	n = normalized.Join(c, o)
-	return n, nil
+	n = normalized.Join(c.Entries, records)
+	return n, c, o, nil
 }
 
 func inspect(norm []*normalized.Normalized) (Return, error) {
@@ -81,8 +127,241 @@ type Return struct {
 	// A record in the CCADB was found whose "OneCRL Status"
 	// is the empty string.
 	NoRevocationStatus []*normalized.Normalized
+
+	// OneCRL records that revoke by subject and public key hash,
+	// which cannot be compared against the CCADB report.
+	SubjectKeyOneCRLRecords []*oneCRL.OneCRLIntermediate
+	// OneCRL records that have neither an issuer and serial number
+	// nor a subject and public key hash.
+	UnrecognizedOneCRLRecords []*oneCRL.OneCRLIntermediate
+	// OneCRL records that carry an attachment in place of a revocation.
+	// The attachment is verified, but what it holds is not compared.
+	UnresolvedOneCRLRecords []*oneCRL.OneCRLIntermediate
+	// OneCRL records that could not be decoded, such as those whose
+	// issuerName or serialNumber is not valid base64.
+	InvalidOneCRLRecords []oneCRL.InvalidRecord
+	// OneCRL records that are not enabled, and so are left
+	// out of the comparison unless include_disabled is set.
+	DisabledOneCRLRecords []*oneCRL.OneCRLIntermediate
+	// How the OneCRL records deviate from the fields that this
+	// tool expects of them, should the Kinto schema change.
+	OneCRLSchemaWarnings oneCRL.SchemaWarnings
+	// OneCRL records whose details do not reference a bug,
+	// only computed if missing_bug_reference is set.
+	MissingBugReferenceOneCRLRecords []*oneCRL.OneCRLIntermediate
+	// The CCADB rows that were left out of the join because another
+	// row for the same certificate was preferred to them.
+	DiscardedCCADBRows []ccadb.Duplicate
+	// Issuers and serial numbers that more than one CCADB row, or
+	// more than one OneCRL record, bears.
+	DuplicatedIssuerSerials []duplicatedSerial
+	// OneCRL records whose attached certificate has another issuer or
+	// serial number, only computed if check_attachments is set.
+	KintoAttachmentMismatches []oneCRL.AttachmentMismatch
+	// The number of entries that limit left out of each category,
+	// keyed by the name of the category.
+	OmittedEntries map[string]int `json:",omitempty"`
+}
+
+// Summary describes the diff in a single line, for the log.
+func (r Return) Summary() string {
+	entries := len(r.AddedAndPresentInOneCRL) + len(r.ExpiredAndPresentInOneCRL) + len(r.ReadyToAddAndPresentInOneCRL) +
+		len(r.AbsentFromCCADBAndPresentInOneCRL) + len(r.AddedAndAbsentFromOneCRL) + len(r.ExpiredAndAbsentFromOneCRL) +
+		len(r.ReadyToAddAndAbsentFromOneCRL) + len(r.AbsentFromCCADBAndAbsentFromOneCRL) + len(r.NoRevocationStatus)
+	discrepancies := len(r.ExpiredAndPresentInOneCRL) + len(r.ReadyToAddAndPresentInOneCRL) +
+		len(r.AbsentFromCCADBAndPresentInOneCRL) + len(r.AddedAndAbsentFromOneCRL)
+	return fmt.Sprintf("%d entries; %d discrepancies (added but absent: %d, expired but present: %d, ready but present: %d, absent from CCADB but present: %d)",
+		entries, discrepancies, len(r.AddedAndAbsentFromOneCRL), len(r.ExpiredAndPresentInOneCRL),
+		len(r.ReadyToAddAndPresentInOneCRL), len(r.AbsentFromCCADBAndPresentInOneCRL))
+}
+
+// categoryNames are the names of the categories of the diff, in the order of their fields.
+var categoryNames = []string{
+	"AddedAndPresentInOneCRL",
+	"ExpiredAndPresentInOneCRL",
+	"ReadyToAddAndPresentInOneCRL",
+	"AbsentFromCCADBAndPresentInOneCRL",
+	"AddedAndAbsentFromOneCRL",
+	"ExpiredAndAbsentFromOneCRL",
+	"ReadyToAddAndAbsentFromOneCRL",
+	"AbsentFromCCADBAndAbsentFromOneCRL",
+	"NoRevocationStatus",
+}
+
+// categories are the categories of the diff whose entries are joined from both sources, keyed by name.
+func (r *Return) categories() map[string]*[]*normalized.Normalized {
+	return map[string]*[]*normalized.Normalized{
+		"AddedAndPresentInOneCRL":            &r.AddedAndPresentInOneCRL,
+		"ExpiredAndPresentInOneCRL":          &r.ExpiredAndPresentInOneCRL,
+		"ReadyToAddAndPresentInOneCRL":       &r.ReadyToAddAndPresentInOneCRL,
+		"AbsentFromCCADBAndPresentInOneCRL":  &r.AbsentFromCCADBAndPresentInOneCRL,
+		"AddedAndAbsentFromOneCRL":           &r.AddedAndAbsentFromOneCRL,
+		"ExpiredAndAbsentFromOneCRL":         &r.ExpiredAndAbsentFromOneCRL,
+		"ReadyToAddAndAbsentFromOneCRL":      &r.ReadyToAddAndAbsentFromOneCRL,
+		"AbsentFromCCADBAndAbsentFromOneCRL": &r.AbsentFromCCADBAndAbsentFromOneCRL,
+		"NoRevocationStatus":                 &r.NoRevocationStatus,
+	}
+}
+
+// FilterOrganization restricts every category of the diff to the entries whose
+// issuer organization name contains substr, ignoring case.
+func (r Return) FilterOrganization(substr string) Return {
+	substr = strings.ToLower(substr)
+	for _, entries := range r.categories() {
+		kept := make([]*normalized.Normalized, 0)
+		for _, n := range *entries {
+			if strings.Contains(strings.ToLower(n.Organization()), substr) {
+				kept = append(kept, n)
+			}
+		}
+		*entries = kept
+	}
+	return r
+}
+
+// FilterRevokedSince restricts every category of the diff to the entries whose CCADB row was
+// revoked on or after since, by its Date of Revocation. A row without a date that can be parsed
+// is left out, while entries absent from the CCADB have no row to filter by and are kept.
+func (r Return) FilterRevokedSince(since time.Time) Return {
+	for _, entries := range r.categories() {
+		kept := make([]*normalized.Normalized, 0)
+		for _, n := range *entries {
+			if n.Entry == nil {
+				kept = append(kept, n)
+				continue
+			}
+			if revoked, err := n.Entry.RevokedOn(); err == nil && !revoked.Before(since) {
+				kept = append(kept, n)
+			}
+		}
+		*entries = kept
+	}
+	return r
+}
+
+// ExcludeExpired removes from every category of the diff the entries whose CCADB row is for a
+// certificate that had expired by now, by its Valid To date. A row without a date that can be
+// parsed is kept, as nothing says that it has expired, as are entries absent from the CCADB.
+func (r Return) ExcludeExpired(now time.Time) Return {
+	for _, entries := range r.categories() {
+		kept := make([]*normalized.Normalized, 0)
+		for _, n := range *entries {
+			if n.Entry != nil {
+				if expired, err := n.Entry.ExpiredAt(now); err == nil && expired {
+					continue
+				}
+			}
+			kept = append(kept, n)
+		}
+		*entries = kept
+	}
+	return r
+}
+
+// Truncate sorts each category of the diff by issuer organization, issuer common name, and serial
+// number, and keeps only its first n entries, counting those that it leaves out in OmittedEntries.
+func (r Return) Truncate(n int) Return {
+	r.OmittedEntries = make(map[string]int)
+	for name, entries := range r.categories() {
+		sorted := append([]*normalized.Normalized(nil), *entries...)
+		sort.SliceStable(sorted, func(i, j int) bool {
+			a, b := sorted[i], sorted[j]
+			if a.Organization() != b.Organization() {
+				return a.Organization() < b.Organization()
+			}
+			if a.CommonName() != b.CommonName() {
+				return a.CommonName() < b.CommonName()
+			}
+			return a.HexSerial() < b.HexSerial()
+		})
+		if len(sorted) > n {
+			r.OmittedEntries[name] = len(sorted) - n
+			sorted = sorted[:n]
+		}
+		*entries = sorted
+	}
+	return r
+}
+
+// Markdown renders each non-empty category of the diff as a GitHub flavored Markdown table of
+// the issuer common name, issuer organization, and serial number of its entries, for pasting
+// into issues and pull requests.
+func (r Return) Markdown() string {
+	b := new(strings.Builder)
+	categories := r.categories()
+	for _, name := range categoryNames {
+		entries := *categories[name]
+		if len(entries) == 0 {
+			continue
+		}
+		if b.Len() > 0 {
+			b.WriteString("\n")
+		}
+		omitted := r.OmittedEntries[name]
+		fmt.Fprintf(b, "## %s (%d)\n\n", name, len(entries)+omitted)
+		b.WriteString("| Issuer CN | Issuer Organization | Serial |\n")
+		b.WriteString("| --- | --- | --- |\n")
+		for _, n := range entries {
+			fmt.Fprintf(b, "| %s | %s | %s |\n", markdownCell(n.CommonName()), markdownCell(n.Organization()), markdownCell(n.HexSerial()))
+		}
+		if omitted > 0 {
+			fmt.Fprintf(b, "\n%d more entries are omitted.\n", omitted)
+		}
+	}
+	if b.Len() == 0 {
+		return "The diff is empty.\n"
+	}
+	return b.String()
+}
+
+// markdownCell escapes the text so that it stays within a single cell of a Markdown table.
+func markdownCell(text string) string {
+	text = strings.ReplaceAll(text, "\\", "\\\\")
+	text = strings.ReplaceAll(text, "|", "\\|")
+	return strings.Join(strings.Fields(text), " ")
+}
+
+// OrganizationGroup is the entries of a category of the diff that share an issuer organization.
+type OrganizationGroup struct {
+	Organization string
+	Entries      []*normalized.Normalized
 }
 
+// GroupByOrganization pivots each populated category of the diff, keyed by its name, into
+// groups of the entries that share an issuer organization. The groups are ordered from the
+// largest to the smallest, so that the CAs responsible for the most divergences come first.
+func (r Return) GroupByOrganization() map[string][]OrganizationGroup {
+	grouped := make(map[string][]OrganizationGroup)
+	for name, entries := range r.categories() {
+		if len(*entries) == 0 {
+			continue
+		}
+		byOrg := make(map[string][]*normalized.Normalized)
+		for _, n := range *entries {
+			byOrg[n.Organization()] = append(byOrg[n.Organization()], n)
+		}
+		groups := make([]OrganizationGroup, 0, len(byOrg))
+		for org, members := range byOrg {
+			groups = append(groups, OrganizationGroup{Organization: org, Entries: members})
+		}
+		sort.Slice(groups, func(i, j int) bool {
+			if len(groups[i].Entries) != len(groups[j].Entries) {
+				return len(groups[i].Entries) > len(groups[j].Entries)
+			}
+			return groups[i].Organization < groups[j].Organization
+		})
+		grouped[name] = groups
+	}
+	return grouped
+}
+
+// quiet suppresses the summary that is otherwise logged for each diff, and the
+// log of which OneCRL collection each request retrieves.
+var quiet = false
+
+// ccadbReport is the location of the CCADB report that the endpoints read.
+var ccadbReport = ccadb.Report
+
 func NewReturn() Return {
 	return Return{
 		make([]*normalized.Normalized, 0),
@@ -94,23 +373,630 @@ func NewReturn() Return {
 		make([]*normalized.Normalized, 0),
 		make([]*normalized.Normalized, 0),
 		make([]*normalized.Normalized, 0),
+		make([]*oneCRL.OneCRLIntermediate, 0),
+		make([]*oneCRL.OneCRLIntermediate, 0),
+		make([]*oneCRL.OneCRLIntermediate, 0),
+		make([]oneCRL.InvalidRecord, 0),
+		make([]*oneCRL.OneCRLIntermediate, 0),
+		oneCRL.SchemaWarnings{Unexpected: make(map[string]int), Missing: make(map[string]int)},
+		make([]*oneCRL.OneCRLIntermediate, 0),
+		make([]ccadb.Duplicate, 0),
+		make([]duplicatedSerial, 0),
+		make([]oneCRL.AttachmentMismatch, 0),
+		nil,
+	}
+}
+
+// oneCRLEndpoint returns the OneCRL URL that the request asks for, either by naming a Kinto
+// environment with "kinto_env" or by giving the URL itself with "kinto_url". Caller supplied
+// URLs are reported as custom, and are never cached so that arbitrary URLs cannot fill the cache.
+func oneCRLEndpoint(r *http.Request) (string, bool, error) {
+	env, raw := r.URL.Query().Get("kinto_env"), r.URL.Query().Get("kinto_url")
+	if raw == "" {
+		url, err := oneCRL.Endpoint(env)
+		return url, false, err
+	}
+	if env != "" {
+		return "", false, errors.New(`at most one of "kinto_env" and "kinto_url" may be given`)
+	}
+	url, err := oneCRL.ParseURL(raw)
+	return url, true, err
+}
+
+// retrieve retrieves the OneCRL collection at the given URL, from the cache unless the URL is custom.
+func retrieve(oneCRLURL string, custom bool) (*oneCRL.Collection, error) {
+	if custom {
+		return oneCRL.RetrieveCollection(oneCRLURL)
+	}
+	return oneCRL.RetrieveCachedCollection(oneCRLURL)
+}
+
+// upstreamStatus is the status with which to answer a request that failed with the given error.
+// Kinto refusing the request, such as for bad credentials or a missing collection, or the CCADB
+// report having a row that cannot be read, is a bad gateway, a source timing out is a gateway
+// timeout, and a source rate limiting us leaves us unavailable.
+func upstreamStatus(err error) int {
+	var status *oneCRL.StatusError
+	if errors.As(err, &status) {
+		return 502
+	}
+	var invalid *ccadb.InvalidSerialError
+	if errors.As(err, &invalid) {
+		return 502
 	}
+	var timeout *fetch.TimeoutError
+	if errors.As(err, &timeout) {
+		return 504
+	}
+	var limited *fetch.RateLimitError
+	if errors.As(err, &limited) {
+		return 503
+	}
+	return 500
 }
 
 func endpoint(w http.ResponseWriter, r *http.Request) {
-	built, err := build()
+	oneCRLURL, custom, err := oneCRLEndpoint(r)
 	if err != nil {
-		w.WriteHeader(500)
+		w.WriteHeader(400)
 		w.Write([]byte(err.Error()))
 		return
 	}
+	signature := r.URL.Query().Get("signature")
+	if signature != "" && signature != "verify" && signature != "require" {
+		w.WriteHeader(400)
+		w.Write([]byte(fmt.Sprintf(`"signature" must be one of "verify" or "require", got %q`, signature)))
+		return
+	}
+	verifier, known := oneCRL.VerifierFor(oneCRLURL)
+	if signature != "" && !known {
+		w.WriteHeader(400)
+		w.Write([]byte(`"signature" may only be given for a "kinto_env", since the root that signs any other collection is unknown`))
+		return
+	}
+	w.Header().Set("X-OneCRL-Endpoint", oneCRLURL)
+	if !quiet {
+		fmt.Fprintf(os.Stderr, "retrieving OneCRL from %s (authenticated: %v)\n", oneCRLURL, oneCRL.Authenticates(oneCRLURL))
+	}
+	built, report, collection, err := buildFrom(ccadbReport, oneCRLURL, buildOptions{
+		NoCache:              custom || r.URL.Query().Get("no_cache") == "true",
+		IncludeDisabled:      r.URL.Query().Get("include_disabled") == "true",
+		AllowUnparsedIssuers: r.URL.Query().Get("allow_unparsed_issuers") == "true",
+	})
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	if signature != "" {
+		if err := collection.VerifySignature(verifier); err == nil {
+			w.Header().Set("X-OneCRL-Signature", "valid")
+		} else if signature == "require" {
+			w.WriteHeader(502)
+			w.Write([]byte(err.Error()))
+			return
+		} else {
+			w.Header().Set("X-OneCRL-Signature", "invalid: "+err.Error())
+		}
+	}
+	w.Header().Set("X-OneCRL-Age", strconv.Itoa(int(time.Since(collection.Retrieved).Seconds())))
+	if collection.Timestamp != 0 {
+		w.Header().Set("X-OneCRL-Timestamp", strconv.FormatInt(collection.Timestamp, 10))
+	}
 	ret, err := inspect(built)
 	if err != nil {
 		w.WriteHeader(500)
 		w.Write([]byte(err.Error()))
 		return
 	}
-	j, err := json.MarshalIndent(ret, "", "  ")
+	ret.SubjectKeyOneCRLRecords = collection.SubjectKeyRecords()
+	ret.UnrecognizedOneCRLRecords = collection.Unrecognized()
+	ret.UnresolvedOneCRLRecords = collection.Unresolved
+	ret.InvalidOneCRLRecords = collection.Invalid
+	ret.DisabledOneCRLRecords = collection.Disabled()
+	ret.OneCRLSchemaWarnings = collection.Schema
+	ret.DiscardedCCADBRows = report.Duplicates
+	ret.DuplicatedIssuerSerials = duplicatedSerials(report, collection)
+	if r.URL.Query().Get("missing_bug_reference") == "true" {
+		ret.MissingBugReferenceOneCRLRecords = collection.MissingBugReference()
+	}
+	// Each attachment is a request of its own, so they are only checked when asked for.
+	if r.URL.Query().Get("check_attachments") == "true" {
+		mismatches, err := collection.CheckAttachments()
+		if err != nil {
+			w.WriteHeader(upstreamStatus(err))
+			w.Write([]byte(err.Error()))
+			return
+		}
+		ret.KintoAttachmentMismatches = mismatches
+	}
+	if r.URL.Query().Get("verbose") == "true" {
+		for _, entries := range ret.categories() {
+			for _, n := range *entries {
+				n.IncludeIssuerAttributes()
+			}
+		}
+	}
+	// The summary counts the whole of the diff, however it is then filtered.
+	if !quiet {
+		fmt.Fprintln(os.Stderr, ret.Summary())
+	}
+	checkThresholds(ret, thresholds, thresholdHooks)
+	if org := r.URL.Query().Get("filter_org"); org != "" {
+		ret = ret.FilterOrganization(org)
+	}
+	if since := r.URL.Query().Get("since"); since != "" {
+		t, err := time.Parse("2006-01-02", since)
+		if err != nil {
+			w.WriteHeader(400)
+			w.Write([]byte(fmt.Sprintf(`"since" must be a date such as 2006-01-02, got %q`, since)))
+			return
+		}
+		ret = ret.FilterRevokedSince(t)
+	}
+	if r.URL.Query().Get("exclude_expired") == "true" {
+		ret = ret.ExcludeExpired(time.Now())
+	}
+	if limit := r.URL.Query().Get("limit"); limit != "" {
+		n, err := strconv.Atoi(limit)
+		if err != nil || n < 1 {
+			w.WriteHeader(400)
+			w.Write([]byte(fmt.Sprintf(`"limit" must be a positive number of entries, got %q`, limit)))
+			return
+		}
+		// Groups are ordered by their size, which a truncated category no longer gives.
+		if r.URL.Query().Get("group_by") != "" {
+			w.WriteHeader(400)
+			w.Write([]byte("a truncated diff cannot be grouped"))
+			return
+		}
+		ret = ret.Truncate(n)
+	}
+	switch format := r.URL.Query().Get("format"); format {
+	case "", "json":
+	case "markdown":
+		if r.URL.Query().Get("group_by") != "" {
+			w.WriteHeader(400)
+			w.Write([]byte("a Markdown diff cannot be grouped"))
+			return
+		}
+		w.Header().Set("Content-Type", "text/markdown; charset=utf-8")
+		w.WriteHeader(200)
+		w.Write([]byte(ret.Markdown()))
+		return
+	default:
+		w.WriteHeader(400)
+		w.Write([]byte(fmt.Sprintf(`"format" must be one of "json" or "markdown", got %q`, format)))
+		return
+	}
+	var body interface{} = ret
+	switch groupBy := r.URL.Query().Get("group_by"); groupBy {
+	case "":
+	case "organization":
+		body = ret.GroupByOrganization()
+	default:
+		w.WriteHeader(400)
+		w.Write([]byte(fmt.Sprintf("cannot group the diff by %q, only by organization", groupBy)))
+		return
+	}
+	j, err := json.MarshalIndent(body, "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// duplicatesEndpoint lists the groups of OneCRL records that revoke the same certificate.
+func duplicatesEndpoint(w http.ResponseWriter, r *http.Request) {
+	oneCRLURL, custom, err := oneCRLEndpoint(r)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.Header().Set("X-OneCRL-Endpoint", oneCRLURL)
+	collection, err := retrieve(oneCRLURL, custom)
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	j, err := json.MarshalIndent(collection.Duplicates(), "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// revokedEndpoint lists every issuer and serial number pair that OneCRL revokes, regardless of the CCADB.
+func revokedEndpoint(w http.ResponseWriter, r *http.Request) {
+	oneCRLURL, custom, err := oneCRLEndpoint(r)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.Header().Set("X-OneCRL-Endpoint", oneCRLURL)
+	collection, err := retrieve(oneCRLURL, custom)
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	j, err := json.MarshalIndent(collection.Revocations().RevokedSerials(), "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// pendingEndpoint lists the changes to OneCRL that are awaiting sign-off in the preview bucket.
+func pendingEndpoint(w http.ResponseWriter, r *http.Request) {
+	collections := make(map[string]*oneCRL.Collection)
+	for _, env := range []string{oneCRL.Production, oneCRL.Preview} {
+		oneCRLURL, err := oneCRL.Endpoint(env)
+		if err != nil {
+			w.WriteHeader(500)
+			w.Write([]byte(err.Error()))
+			return
+		}
+		if !quiet {
+			fmt.Fprintf(os.Stderr, "retrieving OneCRL from %s (authenticated: %v)\n", oneCRLURL, oneCRL.Authenticates(oneCRLURL))
+		}
+		collections[env], err = oneCRL.RetrieveCachedCollection(oneCRLURL)
+		if err != nil {
+			w.WriteHeader(upstreamStatus(err))
+			w.Write([]byte(err.Error()))
+			return
+		}
+	}
+	j, err := json.MarshalIndent(oneCRL.PendingChanges(collections[oneCRL.Production], collections[oneCRL.Preview]), "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// healthEndpoint reports the proportion of OneCRL issuer names that parse, as a canary for
+// changes to how Kinto encodes them.
+func healthEndpoint(w http.ResponseWriter, r *http.Request) {
+	oneCRLURL, custom, err := oneCRLEndpoint(r)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.Header().Set("X-OneCRL-Endpoint", oneCRLURL)
+	collection, err := retrieve(oneCRLURL, custom)
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	j, err := json.MarshalIndent(collection.IssuerHealth(), "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// compareEndpoint compares the two Kinto collections given as the "first" and "second" query parameters.
+func compareEndpoint(w http.ResponseWriter, r *http.Request) {
+	collections := make(map[string]*oneCRL.Collection)
+	for _, param := range []string{"first", "second"} {
+		oneCRLURL, err := oneCRL.ParseURL(r.URL.Query().Get(param))
+		if err != nil {
+			w.WriteHeader(400)
+			w.Write([]byte(fmt.Sprintf("%q: %s", param, err)))
+			return
+		}
+		collections[param], err = oneCRL.RetrieveCollection(oneCRLURL)
+		if err != nil {
+			w.WriteHeader(upstreamStatus(err))
+			w.Write([]byte(err.Error()))
+			return
+		}
+	}
+	j, err := json.MarshalIndent(oneCRL.Compare(collections["first"], collections["second"]), "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// revocationsComparison is the response of revocationsEndpoint when a revocations.txt is posted to it.
+type revocationsComparison struct {
+	OnlyInFile  *oneCRL.Revocations
+	OnlyInKinto *oneCRL.Revocations
+	// The comments and blank lines of the file that were passed over.
+	Skipped oneCRL.Skipped
+	// The blocks and revocations that the file repeats, only computed if revocations_duplicates is set.
+	RevocationsDuplicates []oneCRL.Duplicate `json:",omitempty"`
+	// OnlyInFile with the lines of the file on which each entry appears, only given if line_numbers is set.
+	OnlyInFileSerials      []oneCRL.Block `json:",omitempty"`
+	OnlyInFilePubKeyHashes []oneCRL.Block `json:",omitempty"`
+	// Summaries of the file and of OneCRL.
+	FileStats  oneCRL.Stats
+	KintoStats oneCRL.Stats
+	// The SHA-256 and length of the file, to tell which file a comparison was made against.
+	FileDigest oneCRL.Digest
+}
+
+// revocationsEndpoint renders OneCRL in the format of Firefox's revocations.txt or, should a
+// revocations.txt be posted to it, compares that file against OneCRL (or, with format=canonical,
+// renders that file in canonical form instead).
+func revocationsEndpoint(w http.ResponseWriter, r *http.Request) {
+	oneCRLURL, custom, err := oneCRLEndpoint(r)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	format := r.URL.Query().Get("format")
+	if format != "" && format != "canonical" {
+		w.WriteHeader(400)
+		w.Write([]byte(fmt.Sprintf(`"format" must be "canonical", got %q`, format)))
+		return
+	}
+	duplicates := r.URL.Query().Get("revocations_duplicates") == "true"
+	// The file is digested as it is read, and only held in memory in full should its duplicates be wanted.
+	body := new(bytes.Buffer)
+	digester := oneCRL.NewDigester()
+	var file *oneCRL.Revocations
+	var skipped oneCRL.Skipped
+	if r.Method == http.MethodPost {
+		var sink io.Writer = digester
+		if duplicates {
+			sink = io.MultiWriter(digester, body)
+		}
+		file, skipped, err = oneCRL.ReadRevocationsTxt(io.TeeReader(fetch.Limit(r.Body, oneCRL.MaxResponseSize), sink))
+		if err != nil {
+			writeRevocationsTxtError(w, err)
+			return
+		}
+		if format == "canonical" {
+			// Canonicalizing the file alone has no need of OneCRL.
+			w.Header().Set("Content-Type", "text/plain; charset=utf-8")
+			w.WriteHeader(200)
+			w.Write([]byte(file.String()))
+			return
+		}
+	}
+	w.Header().Set("X-OneCRL-Endpoint", oneCRLURL)
+	collection, err := retrieve(oneCRLURL, custom)
+	if err != nil {
+		w.WriteHeader(upstreamStatus(err))
+		w.Write([]byte(err.Error()))
+		return
+	}
+	if r.Method != http.MethodPost {
+		// OneCRL is always rendered in canonical form.
+		w.Header().Set("Content-Type", "text/plain; charset=utf-8")
+		w.WriteHeader(200)
+		w.Write([]byte(collection.RevocationsTxt()))
+		return
+	}
+	kinto := collection.Revocations()
+	comparison := revocationsComparison{
+		OnlyInFile:  file.Minus(kinto),
+		OnlyInKinto: kinto.Minus(file),
+		Skipped:     skipped,
+		FileDigest:  digester.Digest(),
+		FileStats:   file.Stats(),
+		KintoStats:  kinto.Stats(),
+	}
+	if duplicates {
+		comparison.RevocationsDuplicates = oneCRL.DuplicatesInRevocationsTxt(body.String())
+	}
+	if r.URL.Query().Get("line_numbers") == "true" {
+		comparison.OnlyInFileSerials = comparison.OnlyInFile.SerialBlocksWithLines()
+		comparison.OnlyInFilePubKeyHashes = comparison.OnlyInFile.PubKeyHashBlocksWithLines()
+	}
+	j, err := json.MarshalIndent(comparison, "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// revocationsDiff is the response of revocationsDiffEndpoint.
+type revocationsDiff struct {
+	First  oneCRL.Counts
+	Second oneCRL.Counts
+	// The SHA-256 and length of each file.
+	FirstDigest  oneCRL.Digest
+	SecondDigest oneCRL.Digest
+	// The revocations that the second file adds to, or removes from, the first. With line_numbers
+	// set, each also gives the lines on which it appears in the second file, or the first.
+	AddedSerials        []oneCRL.Block
+	RemovedSerials      []oneCRL.Block
+	AddedPubKeyHashes   []oneCRL.Block
+	RemovedPubKeyHashes []oneCRL.Block
+}
+
+// revocationsDiffEndpoint compares the two revocations.txt posted to it as the "first" and
+// "second" form fields, regardless of the order in which either lists its revocations.
+func revocationsDiffEndpoint(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		w.WriteHeader(405)
+		w.Write([]byte("POST the first and second revocations.txt to be compared"))
+		return
+	}
+	r.Body = http.MaxBytesReader(w, r.Body, 2*oneCRL.MaxResponseSize)
+	files := make(map[string]*oneCRL.Revocations)
+	digests := make(map[string]oneCRL.Digest)
+	for _, field := range []string{"first", "second"} {
+		text, err := formFile(r, field)
+		if err != nil {
+			w.WriteHeader(400)
+			w.Write([]byte(fmt.Sprintf("%q: %s", field, err)))
+			return
+		}
+		digests[field] = oneCRL.DigestOf(text)
+		files[field], err = oneCRL.ParseRevocationsTxt(text)
+		if err != nil {
+			w.WriteHeader(422)
+			w.Write([]byte(fmt.Sprintf("the %s revocations.txt is invalid: %s", field, err)))
+			return
+		}
+	}
+	first, second := files["first"], files["second"]
+	added, removed := second.Minus(first), first.Minus(second)
+	diff := revocationsDiff{
+		First:               first.Counts(),
+		Second:              second.Counts(),
+		FirstDigest:         digests["first"],
+		SecondDigest:        digests["second"],
+		AddedSerials:        added.SerialBlocks(),
+		RemovedSerials:      removed.SerialBlocks(),
+		AddedPubKeyHashes:   added.PubKeyHashBlocks(),
+		RemovedPubKeyHashes: removed.PubKeyHashBlocks(),
+	}
+	if r.URL.Query().Get("line_numbers") == "true" {
+		diff.AddedSerials, diff.RemovedSerials = added.SerialBlocksWithLines(), removed.SerialBlocksWithLines()
+		diff.AddedPubKeyHashes, diff.RemovedPubKeyHashes = added.PubKeyHashBlocksWithLines(), removed.PubKeyHashBlocksWithLines()
+	}
+	j, err := json.MarshalIndent(diff, "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// formFile returns the named field of a posted form, be it an uploaded file or a plain value.
+func formFile(r *http.Request, name string) (string, error) {
+	file, _, err := r.FormFile(name)
+	if err == nil {
+		defer file.Close()
+		raw, err := io.ReadAll(file)
+		return string(raw), err
+	}
+	if values, ok := r.PostForm[name]; ok {
+		return values[0], nil
+	}
+	if err != http.ErrMissingFile && err != http.ErrNotMultipart {
+		return "", err
+	}
+	return "", errors.New("no such field was posted")
+}
+
+// writeRevocationsTxtError answers a request whose revocations.txt could not be read with a
+// 400, or with a 422 should it have been read but not parse, as it is then unprocessable.
+func writeRevocationsTxtError(w http.ResponseWriter, err error) {
+	var syntax *oneCRL.SyntaxError
+	if errors.As(err, &syntax) {
+		w.WriteHeader(422)
+		w.Write([]byte("the revocations.txt is invalid: " + err.Error()))
+		return
+	}
+	w.WriteHeader(400)
+	w.Write([]byte("the revocations.txt could not be read: " + err.Error()))
+}
+
+// statsEndpoint summarizes the revocations.txt posted to it.
+func statsEndpoint(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		w.WriteHeader(405)
+		w.Write([]byte("POST a revocations.txt to be summarized"))
+		return
+	}
+	file, _, err := oneCRL.ReadRevocationsTxt(fetch.Limit(r.Body, oneCRL.MaxResponseSize))
+	if err != nil {
+		writeRevocationsTxtError(w, err)
+		return
+	}
+	j, err := json.MarshalIndent(file.Stats(), "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// lintFix is the response of lintEndpoint when fix is set.
+type lintFix struct {
+	oneCRL.Lint
+	// The file with its findings fixed, and what was changed to fix them.
+	Fixed   string
+	Changes []oneCRL.Change
+}
+
+// lintEndpoint checks the revocations.txt posted to it, and with fix set, fixes it too.
+func lintEndpoint(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		w.WriteHeader(405)
+		w.Write([]byte("POST a revocations.txt to be checked"))
+		return
+	}
+	body, err := io.ReadAll(fetch.Limit(r.Body, oneCRL.MaxResponseSize))
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	strict := r.URL.Query().Get("strict") == "true"
+	var response interface{} = oneCRL.LintRevocationsTxt(string(body), strict)
+	if r.URL.Query().Get("fix") == "true" {
+		f := oneCRL.FixRevocationsTxt(string(body))
+		response = lintFix{Lint: response.(oneCRL.Lint), Fixed: f.Text, Changes: f.Changes}
+	}
+	j, err := json.MarshalIndent(response, "", "  ")
+	if err != nil {
+		w.WriteHeader(500)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.WriteHeader(200)
+	w.Write(j)
+}
+
+// diagnostics is the response of diagnosticsEndpoint.
+type diagnostics struct {
+	Kinto oneCRL.Diagnostics
+	// Empty if the CCADB report is reachable, or else why it is not.
+	CCADBError string `json:",omitempty"`
+}
+
+// diagnosticsEndpoint probes the health of the upstream sources, for when the diff fails.
+func diagnosticsEndpoint(w http.ResponseWriter, r *http.Request) {
+	oneCRLURL, _, err := oneCRLEndpoint(r)
+	if err != nil {
+		w.WriteHeader(400)
+		w.Write([]byte(err.Error()))
+		return
+	}
+	w.Header().Set("X-OneCRL-Endpoint", oneCRLURL)
+	d := diagnostics{Kinto: oneCRL.Probe(oneCRLURL)}
+	if err := ccadb.Reachable(ccadbReport); err != nil {
+		d.CCADBError = err.Error()
+	}
+	j, err := json.MarshalIndent(d, "", "  ")
 	if err != nil {
 		w.WriteHeader(500)
 		w.Write([]byte(err.Error()))
@@ -121,7 +1007,75 @@ func endpoint(w http.ResponseWriter, r *http.Request) {
 }
 
 func main() {
+	if columns := os.Getenv("CCADB_COLUMNS"); columns != "" {
+		if err := json.Unmarshal([]byte(columns), &ccadb.Columns); err != nil {
+			fmt.Fprintf(os.Stderr, "CCADB_COLUMNS is not a JSON object of column names: %s\n", err)
+			os.Exit(1)
+		}
+	}
+	for env, policy := range map[string]*fetch.Policy{"CCADB_TIMEOUT": &ccadb.RetryPolicy, "KINTO_TIMEOUT": &oneCRL.RetryPolicy} {
+		if timeout := os.Getenv(env); timeout != "" {
+			d, err := time.ParseDuration(timeout)
+			if err != nil {
+				fmt.Fprintf(os.Stderr, "%s is not a duration: %s\n", env, err)
+				os.Exit(1)
+			}
+			policy.Timeout = d
+		}
+	}
+	if t := os.Getenv("ALERT_THRESHOLDS"); t != "" {
+		if err := json.Unmarshal([]byte(t), &thresholds); err != nil {
+			fmt.Fprintf(os.Stderr, "ALERT_THRESHOLDS is not a JSON object of category sizes: %s\n", err)
+			os.Exit(1)
+		}
+		for name := range thresholds {
+			if _, ok := new(Return).categories()[name]; !ok {
+				fmt.Fprintf(os.Stderr, "ALERT_THRESHOLDS names %q, which is not a category of the diff\n", name)
+				os.Exit(1)
+			}
+		}
+	}
+	if hook := os.Getenv("ALERT_WEBHOOK"); hook != "" {
+		thresholdHooks = append(thresholdHooks, Webhook(hook))
+	}
+	if fraction := os.Getenv("MAX_UNPARSED_ISSUERS"); fraction != "" {
+		f, err := strconv.ParseFloat(fraction, 64)
+		if err != nil || f < 0 || f > 1 {
+			fmt.Fprintf(os.Stderr, "MAX_UNPARSED_ISSUERS is not a fraction between 0 and 1: %q\n", fraction)
+			os.Exit(1)
+		}
+		oneCRL.MaxUnparsedIssuers = f
+	}
+	if agent := os.Getenv("USER_AGENT"); agent != "" {
+		fetch.UserAgent = agent
+	}
+	oneCRL.CacheDir = os.Getenv("CACHE_DIR")
+	quiet = os.Getenv("QUIET") == "true"
+	oneCRL.AllowHTTP = os.Getenv("KINTO_ALLOW_HTTP") == "true"
+	oneCRL.Token = os.Getenv("KINTO_TOKEN")
+	if file := os.Getenv("KINTO_TOKEN_FILE"); file != "" && oneCRL.Token == "" {
+		token, err := os.ReadFile(file)
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "KINTO_TOKEN_FILE could not be read: %s\n", err)
+			os.Exit(1)
+		}
+		oneCRL.Token = strings.TrimSpace(string(token))
+	}
+	if oneCRL.Token != "" {
+		fmt.Fprintln(os.Stderr, "requests to Kinto will be authenticated with a bearer token")
+	}
 	http.HandleFunc("/", endpoint)
+	http.HandleFunc("/kinto/duplicates", duplicatesEndpoint)
+	http.HandleFunc("/kinto/pending", pendingEndpoint)
+	http.HandleFunc("/kinto/health", healthEndpoint)
+	http.HandleFunc("/kinto/compare", compareEndpoint)
+	http.HandleFunc("/kinto/revocations.txt", revocationsEndpoint)
+	http.HandleFunc("/kinto/revoked", revokedEndpoint)
+	http.HandleFunc("/diagnostics", diagnosticsEndpoint)
+	http.HandleFunc("/explain", explainEndpoint)
+	http.HandleFunc("/revocations/lint", lintEndpoint)
+	http.HandleFunc("/revocations/diff", revocationsDiffEndpoint)
+	http.HandleFunc("/revocations/stats", statsEndpoint)
 	var port string
 	switch env := os.Getenv("PORT"); env {
 	case "":
@@ -129,8 +1083,48 @@ func main() {
 	default:
 		port = fmt.Sprintf(":%s", env)
 	}
-	err := http.ListenAndServe(port, nil)
+	grace := 30 * time.Second
+	if timeout := os.Getenv("SHUTDOWN_TIMEOUT"); timeout != "" {
+		d, err := time.ParseDuration(timeout)
+		if err != nil {
+			fmt.Fprintf(os.Stderr, "SHUTDOWN_TIMEOUT is not a duration: %s\n", err)
+			os.Exit(1)
+		}
+		grace = d
+	}
+	listener, err := net.Listen("tcp", port)
 	if err != nil {
-		fmt.Fprint(os.Stderr, err)
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+	// Kubernetes, like most supervisors, sends SIGTERM and then waits out a grace period before killing us.
+	ctx, stop := signal.NotifyContext(context.Background(), syscall.SIGTERM, os.Interrupt)
+	defer stop()
+	if err := serve(ctx, &http.Server{}, listener, grace); err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+}
+
+// serve serves HTTP on the listener until ctx is done, then stops accepting connections and
+// waits up to grace for the requests in flight to be answered in full before returning.
+func serve(ctx context.Context, server *http.Server, listener net.Listener, grace time.Duration) error {
+	served := make(chan error, 1)
+	go func() {
+		served <- server.Serve(listener)
+	}()
+	select {
+	case err := <-served:
+		return err
+	case <-ctx.Done():
+	}
+	fmt.Fprintf(os.Stderr, "shutting down, waiting up to %s for requests in flight\n", grace)
+	shutdown, cancel := context.WithTimeout(context.Background(), grace)
+	defer cancel()
+	if err := server.Shutdown(shutdown); err != nil {
+		return fmt.Errorf("requests were still in flight after %s: %s", grace, err)
 	}
+	// Serve returns ErrServerClosed as soon as Shutdown begins.
+	<-served
+	return nil
 }
@@ -0,0 +1,500 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package main
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/normalized"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
+	"io"
+	"net"
+	"net/http"
+	"net/http/httptest"
+	"net/url"
+	"strings"
+	"testing"
+	"time"
+)
+
+// The issuer in each fixture is C=US, O=Example Org, CN=Example Intermediate CA.
+const issuerName = "MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E="
+
+const ccadbCSV = `"CA Owner","Certificate Issuer Common Name","Certificate Issuer Organization","Certificate Serial Number","SHA-256 Fingerprint","OneCRL Status"
+"Example Owner","Example Intermediate CA","Example Org","0102030405","AA","Added to OneCRL"
+"Example Owner","Example Intermediate CA","Example Org","0A0B0C","BB","Ready to Add"
+`
+
+const oneCRLJSON = `{
+	"data": [
+		{
+			"schema": 1,
+			"details": {"bug": "https://bugzilla.mozilla.org/1", "who": "", "why": "", "name": "", "created": ""},
+			"enabled": true,
+			"issuerName": "` + issuerName + `",
+			"serialNumber": "AQIDBAU=",
+			"id": "present-in-both",
+			"last_modified": 1
+		},
+		{
+			"schema": 1,
+			"details": {"bug": "https://bugzilla.mozilla.org/2", "who": "", "why": "", "name": "", "created": ""},
+			"enabled": true,
+			"issuerName": "` + issuerName + `",
+			"serialNumber": "AQIDBAY=",
+			"id": "absent-from-ccadb",
+			"last_modified": 2
+		}
+	]
+}`
+
+// upstreams serves canned copies of the CCADB report and the OneCRL collection
+// so that the whole pipeline can be exercised without network access.
+func upstreams(t *testing.T) *httptest.Server {
+	mux := http.NewServeMux()
+	mux.HandleFunc("/ccadb", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(ccadbCSV))
+	})
+	mux.HandleFunc("/onecrl", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(oneCRLJSON))
+	})
+	// The same collection, at a path that may be given as a kinto_url.
+	mux.HandleFunc("/buckets/blocklists/collections/certificates/records", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(oneCRLJSON))
+	})
+	mux.HandleFunc("/broken", func(w http.ResponseWriter, r *http.Request) {
+		w.WriteHeader(http.StatusServiceUnavailable)
+	})
+	server := httptest.NewServer(mux)
+	t.Cleanup(server.Close)
+	return server
+}
+
+func TestBuildAndInspectOffline(t *testing.T) {
+	server := upstreams(t)
+	built, _, _, err := buildFrom(server.URL+"/ccadb", server.URL+"/onecrl", buildOptions{NoCache: true})
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(built) != 3 {
+		t.Fatalf("wanted 3 joined entries, got %d", len(built))
+	}
+	ret, err := inspect(built)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(ret.AddedAndPresentInOneCRL) != 1 {
+		t.Errorf("wanted 1 AddedAndPresentInOneCRL, got %d", len(ret.AddedAndPresentInOneCRL))
+	} else if owner := ret.AddedAndPresentInOneCRL[0].CAOwner; owner != "Example Owner" {
+		t.Errorf("wanted the CA owner to be carried through the join, got %q", owner)
+	}
+	if len(ret.ReadyToAddAndAbsentFromOneCRL) != 1 {
+		t.Errorf("wanted 1 ReadyToAddAndAbsentFromOneCRL, got %d", len(ret.ReadyToAddAndAbsentFromOneCRL))
+	}
+	if len(ret.AbsentFromCCADBAndPresentInOneCRL) != 1 {
+		t.Errorf("wanted 1 AbsentFromCCADBAndPresentInOneCRL, got %d", len(ret.AbsentFromCCADBAndPresentInOneCRL))
+	}
+	want := "3 entries; 1 discrepancies (added but absent: 0, expired but present: 0, ready but present: 0, absent from CCADB but present: 1)"
+	if got := ret.Summary(); got != want {
+		t.Errorf("wanted summary %q, got %q", want, got)
+	}
+}
+
+func TestBuildUpstreamError(t *testing.T) {
+	defer func(c, o fetch.Policy) { ccadb.RetryPolicy, oneCRL.RetryPolicy = c, o }(ccadb.RetryPolicy, oneCRL.RetryPolicy)
+	ccadb.RetryPolicy = fetch.Policy{Attempts: 1}
+	oneCRL.RetryPolicy = fetch.Policy{Attempts: 1}
+	server := upstreams(t)
+	if _, _, _, err := buildFrom(server.URL+"/broken", server.URL+"/onecrl", buildOptions{NoCache: true}); err == nil {
+		t.Error("expected an error when the CCADB report is unavailable")
+	}
+	if _, _, _, err := buildFrom(server.URL+"/ccadb", server.URL+"/broken", buildOptions{NoCache: true}); err == nil {
+		t.Error("expected an error when OneCRL is unavailable")
+	}
+}
+
+func TestEndpointRejectsNonHexCCADBSerial(t *testing.T) {
+	defer func(report string, allow bool) { ccadbReport, oneCRL.AllowHTTP = report, allow }(ccadbReport, oneCRL.AllowHTTP)
+	oneCRL.AllowHTTP = true
+	mux := http.NewServeMux()
+	mux.HandleFunc("/ccadb", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(ccadbCSV + `"Example Owner","Example Intermediate CA","Example Org","not hex","CC","Added to OneCRL"` + "\n"))
+	})
+	mux.HandleFunc("/buckets/blocklists/collections/certificates/records", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(oneCRLJSON))
+	})
+	server := httptest.NewServer(mux)
+	defer server.Close()
+	ccadbReport = server.URL + "/ccadb"
+	query := url.Values{"kinto_url": {server.URL + "/buckets/blocklists/collections/certificates/records"}}
+	w := httptest.NewRecorder()
+	endpoint(w, httptest.NewRequest(http.MethodGet, "/?"+query.Encode(), nil))
+	if w.Code != http.StatusBadGateway {
+		t.Errorf("wanted status %d, got %d", http.StatusBadGateway, w.Code)
+	}
+	if !strings.Contains(w.Body.String(), `"not hex"`) {
+		t.Errorf("wanted the offending serial to be named, got %s", w.Body.String())
+	}
+}
+
+func TestBuildRetrievesConcurrently(t *testing.T) {
+	// The CCADB report is withheld until OneCRL has been requested, which
+	// can only happen if the two are not retrieved one after the other.
+	requested := make(chan struct{})
+	mux := http.NewServeMux()
+	mux.HandleFunc("/ccadb", func(w http.ResponseWriter, r *http.Request) {
+		select {
+		case <-requested:
+		case <-time.After(5 * time.Second):
+			t.Error("OneCRL was not requested while the CCADB report was outstanding")
+		}
+		w.Write([]byte(ccadbCSV))
+	})
+	mux.HandleFunc("/onecrl", func(w http.ResponseWriter, r *http.Request) {
+		close(requested)
+		w.Write([]byte(oneCRLJSON))
+	})
+	server := httptest.NewServer(mux)
+	defer server.Close()
+	built, _, _, err := buildFrom(server.URL+"/ccadb", server.URL+"/onecrl", buildOptions{NoCache: true})
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(built) != 3 {
+		t.Errorf("wanted 3 joined entries, got %d", len(built))
+	}
+}
+
+func TestBuildNamesTimedOutSource(t *testing.T) {
+	defer func(c, o fetch.Policy) { ccadb.RetryPolicy, oneCRL.RetryPolicy = c, o }(ccadb.RetryPolicy, oneCRL.RetryPolicy)
+	ccadb.RetryPolicy = fetch.Policy{Source: "CCADB", Attempts: 1, Timeout: 50 * time.Millisecond}
+	oneCRL.RetryPolicy = fetch.Policy{Source: "Kinto", Attempts: 1, Timeout: 50 * time.Millisecond}
+	server := upstreams(t)
+	slow := httptest.NewServer(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		time.Sleep(500 * time.Millisecond)
+	}))
+	defer slow.Close()
+	for source, urls := range map[string][2]string{
+		"CCADB": {slow.URL, server.URL + "/onecrl"},
+		"Kinto": {server.URL + "/ccadb", slow.URL},
+	} {
+		_, _, _, err := buildFrom(urls[0], urls[1], buildOptions{NoCache: true})
+		var timeout *fetch.TimeoutError
+		if !errors.As(err, &timeout) || timeout.Source != source {
+			t.Errorf("wanted %s to time out, got %v", source, err)
+		} else if upstreamStatus(err) != 504 {
+			t.Errorf("wanted a timeout to be answered with a 504, got %d", upstreamStatus(err))
+		}
+	}
+}
+
+func TestFilterOrganization(t *testing.T) {
+	o := new(oneCRL.OneCRLIntermediate)
+	if err := json.Unmarshal([]byte(`{"issuerName": "`+issuerName+`", "serialNumber": "AQIDBAU="}`), o); err != nil {
+		t.Fatal(err)
+	}
+	ret := NewReturn()
+	ret.AddedAndAbsentFromOneCRL = []*normalized.Normalized{
+		normalized.New(&ccadb.Entry{IssuerOrganizationName: "Example Org"}, nil),
+		normalized.New(&ccadb.Entry{IssuerOrganizationName: "Other CA"}, nil),
+	}
+	ret.AbsentFromCCADBAndPresentInOneCRL = []*normalized.Normalized{normalized.New(nil, o)}
+	ret.NoRevocationStatus = []*normalized.Normalized{normalized.New(&ccadb.Entry{IssuerOrganizationName: "Another CA"}, nil)}
+	filtered := ret.FilterOrganization("EXAMPLE")
+	if len(filtered.AddedAndAbsentFromOneCRL) != 1 || filtered.AddedAndAbsentFromOneCRL[0].Organization() != "Example Org" {
+		t.Errorf("wanted only the Example Org entry, got %v", filtered.AddedAndAbsentFromOneCRL)
+	}
+	if len(filtered.AbsentFromCCADBAndPresentInOneCRL) != 1 {
+		t.Errorf("wanted the OneCRL entry to be matched by its parsed organization, got %v", filtered.AbsentFromCCADBAndPresentInOneCRL)
+	}
+	if len(filtered.NoRevocationStatus) != 0 {
+		t.Errorf("wanted no NoRevocationStatus entries, got %v", filtered.NoRevocationStatus)
+	}
+	if len(ret.AddedAndAbsentFromOneCRL) != 2 {
+		t.Errorf("wanted the unfiltered diff to be left alone, got %d entries", len(ret.AddedAndAbsentFromOneCRL))
+	}
+}
+
+func TestFilterRevokedSince(t *testing.T) {
+	o := new(oneCRL.OneCRLIntermediate)
+	if err := json.Unmarshal([]byte(`{"issuerName": "`+issuerName+`", "serialNumber": "AQIDBAU="}`), o); err != nil {
+		t.Fatal(err)
+	}
+	ret := NewReturn()
+	ret.AddedAndPresentInOneCRL = []*normalized.Normalized{
+		normalized.New(&ccadb.Entry{Fingerprint: "older", DateOfRevocation: "2019.03.25"}, o),
+		normalized.New(&ccadb.Entry{Fingerprint: "cutoff", DateOfRevocation: "2019.03.26"}, o),
+		normalized.New(&ccadb.Entry{Fingerprint: "newer", DateOfRevocation: "2020 Jan 02"}, o),
+		normalized.New(&ccadb.Entry{Fingerprint: "undated"}, o),
+	}
+	ret.AbsentFromCCADBAndPresentInOneCRL = []*normalized.Normalized{normalized.New(nil, o)}
+	filtered := ret.FilterRevokedSince(time.Date(2019, time.March, 26, 0, 0, 0, 0, time.UTC))
+	kept := make([]string, 0)
+	for _, n := range filtered.AddedAndPresentInOneCRL {
+		kept = append(kept, n.Fingerprint)
+	}
+	if strings.Join(kept, ",") != "cutoff,newer" {
+		t.Errorf("wanted the rows revoked on or after the cutoff, got %v", kept)
+	}
+	if len(filtered.AbsentFromCCADBAndPresentInOneCRL) != 1 {
+		t.Errorf("wanted the entry absent from the CCADB to be kept, got %v", filtered.AbsentFromCCADBAndPresentInOneCRL)
+	}
+}
+
+func TestExcludeExpired(t *testing.T) {
+	ret := NewReturn()
+	ret.ExpiredAndAbsentFromOneCRL = []*normalized.Normalized{
+		normalized.New(&ccadb.Entry{Fingerprint: "expired", ValidTo: "2019 Mar 26"}, nil),
+		normalized.New(&ccadb.Entry{Fingerprint: "valid", ValidTo: "2030 Mar 26"}, nil),
+		normalized.New(&ccadb.Entry{Fingerprint: "undated"}, nil),
+	}
+	fingerprints := func(r Return) string {
+		kept := make([]string, 0)
+		for _, n := range r.ExpiredAndAbsentFromOneCRL {
+			kept = append(kept, n.Fingerprint)
+		}
+		return strings.Join(kept, ",")
+	}
+	now := time.Date(2024, time.January, 1, 0, 0, 0, 0, time.UTC)
+	if got := fingerprints(ret.ExcludeExpired(now)); got != "valid,undated" {
+		t.Errorf("wanted the expired row to be excluded, got %s", got)
+	}
+	if got := fingerprints(ret); got != "expired,valid,undated" {
+		t.Errorf("wanted the expired row to be included by default, got %s", got)
+	}
+}
+
+func TestGroupByOrganization(t *testing.T) {
+	entry := func(org string) *normalized.Normalized {
+		return normalized.New(&ccadb.Entry{IssuerOrganizationName: org}, nil)
+	}
+	ret := NewReturn()
+	ret.AddedAndAbsentFromOneCRL = []*normalized.Normalized{entry("B CA"), entry("A CA"), entry("B CA"), entry("C CA")}
+	ret.NoRevocationStatus = []*normalized.Normalized{entry("A CA")}
+	grouped := ret.GroupByOrganization()
+	if len(grouped) != 2 {
+		t.Fatalf("wanted only the 2 populated categories, got %v", grouped)
+	}
+	groups := grouped["AddedAndAbsentFromOneCRL"]
+	want := []string{"B CA", "A CA", "C CA"}
+	if len(groups) != len(want) {
+		t.Fatalf("wanted %d groups, got %v", len(want), groups)
+	}
+	for i, g := range groups {
+		if g.Organization != want[i] {
+			t.Errorf("wanted group %d to be %s, got %s", i, want[i], g.Organization)
+		}
+		for _, n := range g.Entries {
+			if n.Organization() != g.Organization {
+				t.Errorf("wanted only %s entries in its group, got one of %s", g.Organization, n.Organization())
+			}
+		}
+	}
+	if len(groups[0].Entries) != 2 {
+		t.Errorf("wanted 2 entries for B CA, got %d", len(groups[0].Entries))
+	}
+	if g := grouped["NoRevocationStatus"]; len(g) != 1 || len(g[0].Entries) != 1 {
+		t.Errorf("wanted a single NoRevocationStatus group, got %v", g)
+	}
+}
+
+func TestTruncate(t *testing.T) {
+	entry := func(org, serial string) *normalized.Normalized {
+		return normalized.New(&ccadb.Entry{IssuerOrganizationName: org, Serial: serial}, nil)
+	}
+	ret := NewReturn()
+	ret.AddedAndAbsentFromOneCRL = []*normalized.Normalized{entry("B CA", "0C"), entry("A CA", "0B"), entry("C CA", "0D"), entry("A CA", "0A")}
+	ret.NoRevocationStatus = []*normalized.Normalized{entry("A CA", "0E")}
+	truncated := ret.Truncate(2)
+	got := make([]string, 0)
+	for _, n := range truncated.AddedAndAbsentFromOneCRL {
+		got = append(got, n.HexSerial())
+	}
+	if len(got) != 2 || got[0] != "0A" || got[1] != "0B" {
+		t.Errorf("wanted the first 2 entries in sorted order, 0A and 0B, got %v", got)
+	}
+	if len(truncated.OmittedEntries) != 1 || truncated.OmittedEntries["AddedAndAbsentFromOneCRL"] != 2 {
+		t.Errorf("wanted 2 omitted AddedAndAbsentFromOneCRL entries alone, got %v", truncated.OmittedEntries)
+	}
+	if len(truncated.NoRevocationStatus) != 1 {
+		t.Errorf("wanted a category within the limit to be left whole, got %v", truncated.NoRevocationStatus)
+	}
+	if len(ret.AddedAndAbsentFromOneCRL) != 4 || !strings.HasPrefix(ret.Summary(), "5 entries") {
+		t.Errorf("wanted the untruncated diff, and its summary, to be left alone, got %s", ret.Summary())
+	}
+	markdown := truncated.Markdown()
+	if !strings.Contains(markdown, "## AddedAndAbsentFromOneCRL (4)") || !strings.Contains(markdown, "2 more entries are omitted.") {
+		t.Errorf("wanted the Markdown to count the omitted entries, got:\n%s", markdown)
+	}
+}
+
+func TestEndpointRejectsTruncatedGroups(t *testing.T) {
+	defer func(report string, allow bool) { ccadbReport, oneCRL.AllowHTTP = report, allow }(ccadbReport, oneCRL.AllowHTTP)
+	oneCRL.AllowHTTP = true
+	server := upstreams(t)
+	ccadbReport = server.URL + "/ccadb"
+	query := url.Values{
+		"kinto_url": {server.URL + "/buckets/blocklists/collections/certificates/records"},
+		"limit":     {"1"},
+		"group_by":  {"organization"},
+	}
+	w := httptest.NewRecorder()
+	endpoint(w, httptest.NewRequest(http.MethodGet, "/?"+query.Encode(), nil))
+	if w.Code != http.StatusBadRequest {
+		t.Errorf("wanted status %d, got %d", http.StatusBadRequest, w.Code)
+	}
+	query.Del("limit")
+	w = httptest.NewRecorder()
+	endpoint(w, httptest.NewRequest(http.MethodGet, "/?"+query.Encode(), nil))
+	if w.Code != http.StatusOK {
+		t.Errorf("wanted status %d without a limit, got %d: %s", http.StatusOK, w.Code, w.Body.String())
+	}
+}
+
+func TestMarkdown(t *testing.T) {
+	o := new(oneCRL.OneCRLIntermediate)
+	if err := json.Unmarshal([]byte(`{"issuerName": "`+issuerName+`", "serialNumber": "AQIDBAU="}`), o); err != nil {
+		t.Fatal(err)
+	}
+	ret := NewReturn()
+	ret.AddedAndAbsentFromOneCRL = []*normalized.Normalized{
+		normalized.New(&ccadb.Entry{IssuerCommonName: "Pipe CA", IssuerOrganizationName: "Pipe | Sons", Serial: "0A0B"}, nil),
+	}
+	ret.AbsentFromCCADBAndPresentInOneCRL = []*normalized.Normalized{normalized.New(nil, o)}
+	markdown := ret.Markdown()
+	want := "## AbsentFromCCADBAndPresentInOneCRL (1)\n\n" +
+		"| Issuer CN | Issuer Organization | Serial |\n" +
+		"| --- | --- | --- |\n" +
+		"| Example Intermediate CA | Example Org | 0102030405 |\n" +
+		"\n" +
+		"## AddedAndAbsentFromOneCRL (1)\n\n" +
+		"| Issuer CN | Issuer Organization | Serial |\n" +
+		"| --- | --- | --- |\n" +
+		"| Pipe CA | Pipe \\| Sons | 0A0B |\n"
+	if markdown != want {
+		t.Errorf("wanted:\n%s\ngot:\n%s", want, markdown)
+	}
+	// Every row of a table must have as many cells as its header.
+	for _, line := range strings.Split(strings.TrimSpace(markdown), "\n") {
+		if strings.HasPrefix(line, "|") && strings.Count(line, "|")-strings.Count(line, "\\|") != 4 {
+			t.Errorf("wanted 3 cells in %q", line)
+		}
+	}
+	if got := NewReturn().Markdown(); got != "The diff is empty.\n" {
+		t.Errorf("wanted an empty diff to say so, got %q", got)
+	}
+}
+
+func TestRevocationsDiffEndpoint(t *testing.T) {
+	form := url.Values{
+		"first":  {issuerName + "\n AQIDBAU=\n AQIDBAY=\n"},
+		"second": {"# Reordered, with one serial removed and another added.\n" + issuerName + "\n AQIDBAc=\n AQIDBAU=\n"},
+	}
+	req := httptest.NewRequest(http.MethodPost, "/revocations/diff", strings.NewReader(form.Encode()))
+	req.Header.Set("Content-Type", "application/x-www-form-urlencoded")
+	w := httptest.NewRecorder()
+	revocationsDiffEndpoint(w, req)
+	if w.Code != 200 {
+		t.Fatalf("wanted a 200, got %d: %s", w.Code, w.Body)
+	}
+	var diff revocationsDiff
+	if err := json.Unmarshal(w.Body.Bytes(), &diff); err != nil {
+		t.Fatal(err)
+	}
+	if diff.First.Serials != 2 || diff.Second.Serials != 2 {
+		t.Errorf("wanted 2 serials on each side, got %+v and %+v", diff.First, diff.Second)
+	}
+	if len(diff.AddedSerials) != 1 || diff.AddedSerials[0].CommonName != "Example Intermediate CA" ||
+		diff.AddedSerials[0].Organization != "Example Org" || len(diff.AddedSerials[0].Revocations) != 1 || diff.AddedSerials[0].Revocations[0] != "AQIDBAc=" {
+		t.Errorf("wanted only AQIDBAc= to be added, got %+v", diff.AddedSerials)
+	}
+	if len(diff.RemovedSerials) != 1 || len(diff.RemovedSerials[0].Revocations) != 1 || diff.RemovedSerials[0].Revocations[0] != "AQIDBAY=" {
+		t.Errorf("wanted only AQIDBAY= to be removed, got %+v", diff.RemovedSerials)
+	}
+	req = httptest.NewRequest(http.MethodPost, "/revocations/diff", strings.NewReader(url.Values{"first": {issuerName}}.Encode()))
+	req.Header.Set("Content-Type", "application/x-www-form-urlencoded")
+	w = httptest.NewRecorder()
+	revocationsDiffEndpoint(w, req)
+	if w.Code != 400 {
+		t.Errorf("wanted a 400 when the second file is missing, got %d", w.Code)
+	}
+}
+
+func TestOneCRLEndpoint(t *testing.T) {
+	want := "https://kinto.example.com/v1/buckets/main/collections/onecrl/records"
+	got, custom, err := oneCRLEndpoint(httptest.NewRequest(http.MethodGet, "/?kinto_url="+want, nil))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if got != want || !custom {
+		t.Errorf("wanted custom URL %s, got %s (custom: %v)", want, got, custom)
+	}
+	got, custom, err = oneCRLEndpoint(httptest.NewRequest(http.MethodGet, "/?kinto_env=prod", nil))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if got != oneCRL.OneCRLEndpoint || custom {
+		t.Errorf("wanted %s, got %s (custom: %v)", oneCRL.OneCRLEndpoint, got, custom)
+	}
+	if _, _, err := oneCRLEndpoint(httptest.NewRequest(http.MethodGet, "/?kinto_env=prod&kinto_url="+want, nil)); err == nil {
+		t.Error("expected an error when both kinto_env and kinto_url are given")
+	}
+}
+
+func TestEndpointRejectsSignatureForCustomURL(t *testing.T) {
+	query := url.Values{"kinto_url": {"https://kinto.example.com/v1/buckets/main/collections/onecrl/records"}, "signature": {"verify"}}
+	w := httptest.NewRecorder()
+	endpoint(w, httptest.NewRequest(http.MethodGet, "/?"+query.Encode(), nil))
+	if w.Code != http.StatusBadRequest {
+		t.Errorf("wanted status %d, got %d", http.StatusBadRequest, w.Code)
+	}
+}
+
+func TestServeShutsDownGracefully(t *testing.T) {
+	listener, err := net.Listen("tcp", "127.0.0.1:0")
+	if err != nil {
+		t.Fatal(err)
+	}
+	started, release := make(chan struct{}), make(chan struct{})
+	server := &http.Server{Handler: http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		close(started)
+		<-release
+		w.Write([]byte("done"))
+	})}
+	ctx, cancel := context.WithCancel(context.Background())
+	served := make(chan error, 1)
+	go func() {
+		served <- serve(ctx, server, listener, 10*time.Second)
+	}()
+	responses := make(chan string, 1)
+	go func() {
+		resp, err := http.Get("http://" + listener.Addr().String())
+		if err != nil {
+			responses <- err.Error()
+			return
+		}
+		defer resp.Body.Close()
+		body, _ := io.ReadAll(resp.Body)
+		responses <- string(body)
+	}()
+	<-started
+	// As though SIGTERM arrived in the middle of a run.
+	cancel()
+	select {
+	case err := <-served:
+		t.Fatalf("wanted serve to wait for the request in flight, but it returned %v", err)
+	case <-time.After(100 * time.Millisecond):
+	}
+	close(release)
+	if body := <-responses; body != "done" {
+		t.Errorf("wanted the request in flight to be answered in full, got %q", body)
+	}
+	if err := <-served; err != nil {
+		t.Errorf("wanted a clean shutdown, got %s", err)
+	}
+}
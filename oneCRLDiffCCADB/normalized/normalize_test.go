@@ -5,8 +5,10 @@
 package normalized
 
 import (
+	"encoding/json"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
+	"strings"
 	"testing"
 )
 
@@ -24,3 +26,65 @@ func TestNormalize(t *testing.T) {
 	t.Log(len(o))
 	t.Log(len(n))
 }
+
+func TestMarshalIncludesOneCRLDetails(t *testing.T) {
+	o := &oneCRL.OneCRLIntermediate{Id: "abc", Enabled: true, Details: oneCRL.Details{Bug: "https://bugzilla.mozilla.org/1"}}
+	j, err := json.Marshal(New(&ccadb.Entry{Serial: "01"}, o))
+	if err != nil {
+		t.Fatal(err)
+	}
+	got := string(j)
+	for _, want := range []string{`"serial":"01"`, `"id":"abc"`, `"bug":"https://bugzilla.mozilla.org/1"`} {
+		if !strings.Contains(got, want) {
+			t.Errorf("wanted %s in %s", want, got)
+		}
+	}
+	j, err = json.Marshal(New(&ccadb.Entry{Serial: "01"}, nil))
+	if err != nil {
+		t.Fatal(err)
+	}
+	if strings.Contains(string(j), "oneCRL") {
+		t.Errorf("wanted no OneCRL record for an entry absent from OneCRL, got %s", j)
+	}
+}
+
+func TestMarshalIdentifiesOneCRLOnlyEntries(t *testing.T) {
+	// C=US, O=Example Org, CN=Example Intermediate CA
+	const issuerName = "MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E="
+	o := new(oneCRL.OneCRLIntermediate)
+	if err := json.Unmarshal([]byte(`{"id": "abc", "issuerName": "`+issuerName+`", "serialNumber": "AQIDBAU="}`), o); err != nil {
+		t.Fatal(err)
+	}
+	j, err := json.Marshal(New(nil, o))
+	if err != nil {
+		t.Fatal(err)
+	}
+	got := string(j)
+	for _, want := range []string{`"issuerName":"` + issuerName + `"`, `"serialNumber":"AQIDBAU="`} {
+		if !strings.Contains(got, want) {
+			t.Errorf("wanted %s in %s", want, got)
+		}
+	}
+}
+
+func TestJoinMatchesSerialAcrossEncodings(t *testing.T) {
+	// C=US, O=Example Org, CN=Example Intermediate CA
+	const issuerName = "MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E="
+	for _, hex := range []string{"00FF01", "ff01", "00:FF:01"} {
+		for _, b64 := range []string{"AP8B", "/wE="} {
+			o := new(oneCRL.OneCRLIntermediate)
+			if err := json.Unmarshal([]byte(`{"issuerName": "`+issuerName+`", "serialNumber": "`+b64+`"}`), o); err != nil {
+				t.Fatal(err)
+			}
+			c := &ccadb.Entry{IssuerCommonName: "Example Intermediate CA", IssuerOrganizationName: "Example Org", Serial: hex}
+			key, err := c.Key()
+			if err != nil {
+				t.Fatal(err)
+			}
+			n := Join(map[string]*ccadb.Entry{key: c}, map[string]*oneCRL.OneCRLIntermediate{o.Key(): o})
+			if len(n) != 1 || n[0].Entry == nil || n[0].OneCRLIntermediate == nil {
+				t.Errorf("CCADB serial %q and OneCRL serial %q did not join, got %d entries", hex, b64, len(n))
+			}
+		}
+	}
+}
@@ -5,6 +5,8 @@
 package normalized
 
 import (
+	"encoding/base64"
+	"encoding/hex"
 	"encoding/json"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/ccadb"
 	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/oneCRL"
@@ -23,6 +25,7 @@ func Join(c map[string]*ccadb.Entry, o map[string]*oneCRL.OneCRLIntermediate) []
 		n := intermediate[key]
 		if n == nil {
 			n = new(Normalized)
+			intermediate[key] = n
 		}
 		n.OneCRLIntermediate = cert
 	}
@@ -36,14 +39,90 @@ func Join(c map[string]*ccadb.Entry, o map[string]*oneCRL.OneCRLIntermediate) []
 type Normalized struct {
 	*ccadb.Entry
 	*oneCRL.OneCRLIntermediate
+	// Every attribute of the OneCRL issuer name, only populated by IncludeIssuerAttributes.
+	IssuerAttributes []oneCRL.Attribute
+}
+
+// record is the portion of a OneCRL record that is reported alongside the CCADB entry
+// so that a reader has the context of the revocation at hand. The issuer and serial number
+// are all that identifies an entry that is absent from the CCADB.
+type record struct {
+	Id           string         `json:"id"`
+	Enabled      bool           `json:"enabled"`
+	IssuerName   oneCRL.Name    `json:"issuerName"`
+	SerialNumber string         `json:"serialNumber"`
+	Details      oneCRL.Details `json:"details"`
 }
 
 func (n Normalized) MarshalJSON() ([]byte, error) {
-	return json.Marshal(n.Entry)
+	out := struct {
+		*ccadb.Entry
+		OneCRL           *record            `json:"oneCRL,omitempty"`
+		IssuerAttributes []oneCRL.Attribute `json:"issuerAttributes,omitempty"`
+	}{Entry: n.Entry, IssuerAttributes: n.IssuerAttributes}
+	if n.OneCRLIntermediate != nil {
+		out.OneCRL = &record{
+			Id:           n.OneCRLIntermediate.Id,
+			Enabled:      n.OneCRLIntermediate.Enabled,
+			IssuerName:   n.OneCRLIntermediate.IssuerName,
+			SerialNumber: n.OneCRLIntermediate.SerialNumber,
+			Details:      n.OneCRLIntermediate.Details,
+		}
+	}
+	return json.Marshal(out)
 }
 
 func New(c *ccadb.Entry, o *oneCRL.OneCRLIntermediate) *Normalized {
-	return &Normalized{c, o}
+	return &Normalized{Entry: c, OneCRLIntermediate: o}
+}
+
+// IncludeIssuerAttributes populates IssuerAttributes from the OneCRL record, if there is one,
+// to help explain why names that look alike were not joined.
+func (n *Normalized) IncludeIssuerAttributes() {
+	if n.OneCRLIntermediate != nil {
+		n.IssuerAttributes = n.OneCRLIntermediate.IssuerName.Attributes()
+	}
+}
+
+// Organization is the issuer organization name of the entry, as the CCADB has it if the
+// entry is present there, or else as OneCRL has it.
+func (n *Normalized) Organization() string {
+	if n.Entry != nil {
+		return n.Entry.IssuerOrganizationName
+	}
+	if n.OneCRLIntermediate != nil {
+		_, org := n.OneCRLIntermediate.IssuerName.Key()
+		return org
+	}
+	return ""
+}
+
+// CommonName is the issuer common name of the entry, as the CCADB has it if the
+// entry is present there, or else as OneCRL has it.
+func (n *Normalized) CommonName() string {
+	if n.Entry != nil {
+		return n.Entry.IssuerCommonName
+	}
+	if n.OneCRLIntermediate != nil {
+		cn, _ := n.OneCRLIntermediate.IssuerName.Key()
+		return cn
+	}
+	return ""
+}
+
+// HexSerial is the serial number of the entry in hex, as the CCADB has it if the entry
+// is present there, or else as decoded from OneCRL's base64.
+func (n *Normalized) HexSerial() string {
+	if n.Entry != nil {
+		return n.Entry.Serial
+	}
+	if n.OneCRLIntermediate != nil {
+		if b, err := base64.StdEncoding.DecodeString(n.OneCRLIntermediate.SerialNumber); err == nil {
+			return strings.ToUpper(hex.EncodeToString(b))
+		}
+		return n.OneCRLIntermediate.SerialNumber
+	}
+	return ""
 }
 
 // The consequent grouping of methods encode the following truth table.
@@ -0,0 +1,161 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"crypto/sha256"
+	"crypto/x509"
+	"encoding/base64"
+	"encoding/hex"
+	"encoding/pem"
+	"fmt"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/serial"
+	"io"
+	"net/http"
+	"net/url"
+	"strings"
+)
+
+// Attachment is a file that a Remote Settings record may carry in place of inline data. It is
+// located relative to the attachments base URL that the Kinto server advertises.
+type Attachment struct {
+	Location string `json:"location"`
+	// The hex encoded SHA-256 digest of the file, and its size in bytes, if they are given.
+	Hash string `json:"hash,omitempty"`
+	Size int64  `json:"size,omitempty"`
+}
+
+// resolveAttachments downloads the attachment of each record that carries one in place of a
+// revocation, and checks it against its hash and size, should the record give them. Kinto defines
+// where an attachment is and how it is verified, but not what it holds, so the records are left
+// Unresolved rather than compared against the CCADB.
+func (c *Collection) resolveAttachments(recordsURL string) error {
+	if len(c.Unresolved) == 0 {
+		return nil
+	}
+	base, err := attachmentsBaseURL(recordsURL)
+	if err != nil {
+		return err
+	}
+	for _, record := range c.Unresolved {
+		if _, err := fetchAttachment(base, record); err != nil {
+			return err
+		}
+	}
+	return nil
+}
+
+// AttachmentMismatch is a OneCRL record whose attached certificate has another issuer or
+// serial number than the record gives.
+type AttachmentMismatch struct {
+	Record *OneCRLIntermediate
+	// The issuer and serial number of the attached certificate, base64 encoded as those of a record are.
+	CertificateIssuerName   string
+	CertificateSerialNumber string
+	// The fields of the record that disagree with the certificate: issuerName, serialNumber, or both.
+	Differing []string
+}
+
+// CheckAttachments downloads the attachment of each record that revokes by issuer and serial
+// number and carries one, which is the PEM encoded certificate that it revokes, and returns the
+// records whose certificate disagrees with them, in the order of Kinto. The issuer must match
+// the certificate's DER exactly, as Firefox matches it, while the serial number is compared by
+// its value. An attachment that cannot be retrieved, verified, or parsed is an error.
+func (c *Collection) CheckAttachments() ([]AttachmentMismatch, error) {
+	mismatches := make([]AttachmentMismatch, 0)
+	base := ""
+	for _, record := range c.Records {
+		if record.Attachment == nil || !record.IsIssuerSerial() {
+			continue
+		}
+		if base == "" {
+			var err error
+			if base, err = attachmentsBaseURL(c.Source); err != nil {
+				return nil, err
+			}
+		}
+		raw, err := fetchAttachment(base, record)
+		if err != nil {
+			return nil, err
+		}
+		block, _ := pem.Decode(raw)
+		if block == nil {
+			return nil, fmt.Errorf("the attachment of OneCRL record %s is not PEM encoded", record.Id)
+		}
+		cert, err := x509.ParseCertificate(block.Bytes)
+		if err != nil {
+			return nil, fmt.Errorf("the attachment of OneCRL record %s is not a certificate: %s", record.Id, err)
+		}
+		m := AttachmentMismatch{
+			Record:                  record,
+			CertificateIssuerName:   base64.StdEncoding.EncodeToString(cert.RawIssuer),
+			CertificateSerialNumber: base64.StdEncoding.EncodeToString(cert.SerialNumber.Bytes()),
+			Differing:               make([]string, 0),
+		}
+		if m.CertificateIssuerName != record.IssuerName.raw {
+			m.Differing = append(m.Differing, "issuerName")
+		}
+		if serial.Canonical(cert.SerialNumber.Bytes()) != record.decodeSerial() {
+			m.Differing = append(m.Differing, "serialNumber")
+		}
+		if len(m.Differing) > 0 {
+			mismatches = append(mismatches, m)
+		}
+	}
+	return mismatches, nil
+}
+
+// attachmentsBaseURL is the location of attachments that the Kinto server behind
+// the records endpoint advertises in its capabilities.
+func attachmentsBaseURL(recordsURL string) (string, error) {
+	server, err := probeServer(serverRoot(recordsURL))
+	if err != nil {
+		return "", err
+	}
+	if server.AttachmentsBaseURL == "" {
+		return "", fmt.Errorf("the OneCRL records at %s carry attachments, but the server advertises no attachments base URL", recordsURL)
+	}
+	return server.AttachmentsBaseURL, nil
+}
+
+// fetchAttachment downloads the attachment of the record from beneath the attachments base URL,
+// and checks it against the hash and size of the record, should it give them.
+func fetchAttachment(base string, record *OneCRLIntermediate) ([]byte, error) {
+	location := strings.TrimSuffix(base, "/") + "/" + strings.TrimPrefix(record.Attachment.Location, "/")
+	u, err := url.Parse(location)
+	if err != nil {
+		return nil, err
+	}
+	if u.Scheme != "https" && !(u.Scheme == "http" && AllowHTTP) {
+		return nil, fmt.Errorf("the attachment of OneCRL record %s is not served over https: %s", record.Id, location)
+	}
+	req, err := http.NewRequest(http.MethodGet, location, nil)
+	if err != nil {
+		return nil, err
+	}
+	resp, err := RetryPolicy.Do(req)
+	if err != nil {
+		return nil, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return nil, &StatusError{URL: location, StatusCode: resp.StatusCode}
+	}
+	raw, err := io.ReadAll(fetch.Limit(resp.Body, MaxResponseSize))
+	if err != nil {
+		return nil, RetryPolicy.Wrap(location, err)
+	}
+	if record.Attachment.Size > 0 && int64(len(raw)) != record.Attachment.Size {
+		return nil, fmt.Errorf("the attachment of OneCRL record %s is %d bytes, wanted %d", record.Id, len(raw), record.Attachment.Size)
+	}
+	if record.Attachment.Hash != "" {
+		sum := sha256.Sum256(raw)
+		if !strings.EqualFold(hex.EncodeToString(sum[:]), record.Attachment.Hash) {
+			return nil, fmt.Errorf("the attachment of OneCRL record %s does not match its hash", record.Id)
+		}
+	}
+	return raw, nil
+}
@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"os"
+	"path/filepath"
+	"sync"
+	"time"
+)
+
+// CacheDir, if set, is a directory in which each cached collection is also written, so that a
+// restarted process revalidates its last download rather than downloading the collection anew.
+// A cache file that cannot be read or written is ignored.
+var CacheDir = ""
+
+// The most recent download of each collection, keyed by records endpoint so that
+// different Kinto environments never share an entry.
+var cache = struct {
+	sync.Mutex
+	collections map[string]*cached
+}{collections: make(map[string]*cached)}
+
+type cached struct {
+	// Held for the duration of a revalidation so that concurrent
+	// requests for the same collection do not race one another.
+	sync.Mutex
+	collection *Collection
+}
+
+// RetrieveCachedCollection is RetrieveCollection, except that the previous download of the same
+// collection is revalidated using its ETag and reused if Kinto reports that it has not changed.
+//
+// The returned Collection may be shared with other callers and must not be modified.
+func RetrieveCachedCollection(url string) (*Collection, error) {
+	cache.Lock()
+	entry, ok := cache.collections[url]
+	if !ok {
+		entry = new(cached)
+		cache.collections[url] = entry
+	}
+	cache.Unlock()
+	entry.Lock()
+	defer entry.Unlock()
+	if entry.collection == nil && CacheDir != "" {
+		entry.collection = readCache(url)
+	}
+	etag := ""
+	if entry.collection != nil {
+		etag = entry.collection.ETag
+	}
+	c, err := retrieveCollection(url, etag)
+	if err == errNotModified {
+		return entry.collection, nil
+	}
+	if err != nil {
+		return nil, err
+	}
+	entry.collection = c
+	if CacheDir != "" {
+		writeCache(url, c)
+	}
+	return c, nil
+}
+
+// diskCollection is the form in which a Collection is written to CacheDir.
+type diskCollection struct {
+	Records    []*OneCRLIntermediate
+	Invalid    []InvalidRecord
+	Unresolved []*OneCRLIntermediate
+	ETag       string
+	Timestamp  int64
+	Source     string
+	Retrieved  time.Time
+	Schema     SchemaWarnings
+	Signed     []json.RawMessage
+	Signature  *signatureMetadata
+}
+
+// cacheFile is the file in CacheDir for the collection at the given URL.
+func cacheFile(url string) string {
+	sum := sha256.Sum256([]byte(url))
+	return filepath.Join(CacheDir, hex.EncodeToString(sum[:])+".json")
+}
+
+// readCache returns the collection that was written for the given URL, or nil if there is none
+// usable. Without an ETag the collection could never be revalidated, so it is not used.
+func readCache(url string) *Collection {
+	raw, err := os.ReadFile(cacheFile(url))
+	if err != nil {
+		return nil
+	}
+	var d diskCollection
+	if err := json.Unmarshal(raw, &d); err != nil || d.ETag == "" || d.Source == "" {
+		return nil
+	}
+	c := newCollection(d.Source)
+	c.Records = append(c.Records, d.Records...)
+	c.Invalid = append(c.Invalid, d.Invalid...)
+	c.Unresolved = append(c.Unresolved, d.Unresolved...)
+	c.ETag = d.ETag
+	c.Timestamp = d.Timestamp
+	c.Retrieved = d.Retrieved
+	if d.Schema.Unexpected != nil && d.Schema.Missing != nil {
+		c.Schema = d.Schema
+	}
+	c.signed = d.Signed
+	c.signature = d.Signature
+	return c
+}
+
+// writeCache writes the collection for the given URL, by way of a temporary file so that
+// a reader never sees it half written.
+func writeCache(url string, c *Collection) {
+	if c.ETag == "" {
+		return
+	}
+	raw, err := json.Marshal(diskCollection{
+		Records:    c.Records,
+		Invalid:    c.Invalid,
+		Unresolved: c.Unresolved,
+		ETag:       c.ETag,
+		Timestamp:  c.Timestamp,
+		Source:     c.Source,
+		Retrieved:  c.Retrieved,
+		Schema:     c.Schema,
+		Signed:     c.signed,
+		Signature:  c.signature,
+	})
+	if err != nil {
+		return
+	}
+	if err := os.MkdirAll(CacheDir, 0700); err != nil {
+		return
+	}
+	tmp, err := os.CreateTemp(CacheDir, "collection-*.tmp")
+	if err != nil {
+		return
+	}
+	_, err = tmp.Write(raw)
+	if closeErr := tmp.Close(); err == nil {
+		err = closeErr
+	}
+	if err != nil {
+		os.Remove(tmp.Name())
+		return
+	}
+	if err := os.Rename(tmp.Name(), cacheFile(url)); err != nil {
+		os.Remove(tmp.Name())
+	}
+}
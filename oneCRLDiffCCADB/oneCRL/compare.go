@@ -0,0 +1,79 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"sort"
+)
+
+// Pending describes the changes to OneCRL that are awaiting sign-off, which is to say
+// the difference between the preview bucket and the main bucket.
+type Pending struct {
+	// Issuer/serial records in preview that are not yet in main.
+	PendingAdditions []*OneCRLIntermediate
+	// Issuer/serial records in main that are no longer in preview.
+	PendingRemovals []*OneCRLIntermediate
+	// The collection timestamps of main and preview.
+	MainTimestamp    int64
+	PreviewTimestamp int64
+}
+
+// PendingChanges compares the main and preview copies of OneCRL by the "key" of their
+// issuer/serial records (as described by Retrieve). Each list is ordered by record id.
+func PendingChanges(main, preview *Collection) Pending {
+	published, staged := main.Intermediates(), preview.Intermediates()
+	return Pending{
+		PendingAdditions: difference(staged, published),
+		PendingRemovals:  difference(published, staged),
+		MainTimestamp:    main.Timestamp,
+		PreviewTimestamp: preview.Timestamp,
+	}
+}
+
+// Summary identifies one side of a Comparison.
+type Summary struct {
+	URL       string
+	Records   int
+	Timestamp int64
+}
+
+// Comparison is the difference between two arbitrary collections, labelled "first" and "second".
+type Comparison struct {
+	First  Summary
+	Second Summary
+	// Issuer/serial records whose keys are found in only one of the two collections.
+	OnlyInFirst  []*OneCRLIntermediate
+	OnlyInSecond []*OneCRLIntermediate
+}
+
+// Compare compares two collections by the "key" of their issuer/serial records (as described
+// by Retrieve). Each list is ordered by record id.
+func Compare(first, second *Collection) Comparison {
+	a, b := first.Intermediates(), second.Intermediates()
+	return Comparison{
+		First:        summarize(first),
+		Second:       summarize(second),
+		OnlyInFirst:  difference(a, b),
+		OnlyInSecond: difference(b, a),
+	}
+}
+
+func summarize(c *Collection) Summary {
+	return Summary{URL: c.Source, Records: len(c.Records), Timestamp: c.Timestamp}
+}
+
+// difference returns the records of a whose keys are absent from b.
+func difference(a, b map[string]*OneCRLIntermediate) []*OneCRLIntermediate {
+	records := make([]*OneCRLIntermediate, 0)
+	for key, record := range a {
+		if _, ok := b[key]; !ok {
+			records = append(records, record)
+		}
+	}
+	sort.Slice(records, func(i, j int) bool {
+		return records[i].Id < records[j].Id
+	})
+	return records
+}
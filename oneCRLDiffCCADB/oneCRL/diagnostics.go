@@ -0,0 +1,103 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"encoding/json"
+	"sort"
+	"strings"
+)
+
+// Server is what a Kinto server advertises about itself in its root document.
+type Server struct {
+	ProjectVersion string
+	HTTPAPIVersion string
+	// The names of the capabilities that the server advertises.
+	Capabilities []string
+	// The location of attachments, if the server advertises the attachments capability.
+	AttachmentsBaseURL string
+	BatchMaxRequests   int
+	Readonly           bool
+}
+
+// Diagnostics describe the health of the Kinto server behind a records endpoint. Any probe
+// that fails records its error in place of its result.
+type Diagnostics struct {
+	Server      *Server
+	ServerError string `json:",omitempty"`
+	// The timestamp of the collection's metadata.
+	CollectionLastModified int64
+	CollectionError        string `json:",omitempty"`
+}
+
+// Probe fetches the root document of the Kinto server and the metadata of the collection
+// behind the given records endpoint.
+func Probe(recordsURL string) Diagnostics {
+	d := Diagnostics{}
+	server, err := probeServer(serverRoot(recordsURL))
+	if err != nil {
+		d.ServerError = err.Error()
+	}
+	d.Server = server
+	var collection struct {
+		Data struct {
+			LastModified int64 `json:"last_modified"`
+		} `json:"data"`
+	}
+	_, err = get(strings.TrimSuffix(recordsURL, "/records"), "", func(dec *json.Decoder) error {
+		return dec.Decode(&collection)
+	})
+	if err != nil {
+		d.CollectionError = err.Error()
+	}
+	d.CollectionLastModified = collection.Data.LastModified
+	return d
+}
+
+// serverRoot returns the root document of the Kinto server behind a records endpoint.
+func serverRoot(recordsURL string) string {
+	if i := strings.Index(recordsURL, "/buckets/"); i >= 0 {
+		return recordsURL[:i+1]
+	}
+	return recordsURL
+}
+
+func probeServer(root string) (*Server, error) {
+	var document struct {
+		ProjectVersion string `json:"project_version"`
+		HTTPAPIVersion string `json:"http_api_version"`
+		Settings       struct {
+			BatchMaxRequests int  `json:"batch_max_requests"`
+			Readonly         bool `json:"readonly"`
+		} `json:"settings"`
+		Capabilities map[string]json.RawMessage `json:"capabilities"`
+	}
+	_, err := get(root, "", func(dec *json.Decoder) error {
+		return dec.Decode(&document)
+	})
+	if err != nil {
+		return nil, err
+	}
+	s := &Server{
+		ProjectVersion:   document.ProjectVersion,
+		HTTPAPIVersion:   document.HTTPAPIVersion,
+		Capabilities:     make([]string, 0, len(document.Capabilities)),
+		BatchMaxRequests: document.Settings.BatchMaxRequests,
+		Readonly:         document.Settings.Readonly,
+	}
+	for name := range document.Capabilities {
+		s.Capabilities = append(s.Capabilities, name)
+	}
+	sort.Strings(s.Capabilities)
+	if raw, ok := document.Capabilities["attachments"]; ok {
+		var attachments struct {
+			BaseURL string `json:"base_url"`
+		}
+		if err := json.Unmarshal(raw, &attachments); err == nil {
+			s.AttachmentsBaseURL = attachments.BaseURL
+		}
+	}
+	return s, nil
+}
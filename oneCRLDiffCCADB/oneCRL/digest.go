@@ -0,0 +1,47 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"crypto/sha256"
+	"encoding/hex"
+	"hash"
+)
+
+// Digest identifies the content of a revocations.txt, so that a result may be correlated with
+// the file that it was computed from.
+type Digest struct {
+	// The hex SHA-256 of the file, and its length in bytes.
+	SHA256 string
+	Length int64
+}
+
+// DigestOf returns the Digest of a file held in full.
+func DigestOf(text string) Digest {
+	d := NewDigester()
+	d.Write([]byte(text))
+	return d.Digest()
+}
+
+// Digester computes the Digest of what is written to it, for a file that is read as a stream.
+type Digester struct {
+	hash   hash.Hash
+	length int64
+}
+
+// NewDigester returns a Digester to which nothing has been written.
+func NewDigester() *Digester {
+	return &Digester{hash: sha256.New()}
+}
+
+func (d *Digester) Write(p []byte) (int, error) {
+	d.length += int64(len(p))
+	return d.hash.Write(p)
+}
+
+// Digest returns the Digest of what has been written so far.
+func (d *Digester) Digest() Digest {
+	return Digest{SHA256: hex.EncodeToString(d.hash.Sum(nil)), Length: d.length}
+}
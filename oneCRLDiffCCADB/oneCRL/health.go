@@ -0,0 +1,81 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"errors"
+	"fmt"
+)
+
+// IssuerHealth tallies how many of the issuer names of a collection could be parsed. A low
+// proportion suggests that Kinto has begun encoding issuers in a manner that we do not expect.
+type IssuerHealth struct {
+	// The records that carry an issuerName.
+	Issuers int
+	// The issuer names that parsed to a Name with a Common Name or an Organization Name.
+	Parsed int
+	// Parsed as a fraction of Issuers, or 1 if there are no issuers.
+	Rate float64
+}
+
+// IssuerHealth checks the issuer names of the records that have already been retrieved.
+func (c *Collection) IssuerHealth() IssuerHealth {
+	h := IssuerHealth{}
+	for _, cert := range c.Records {
+		if cert.IssuerName.raw == "" {
+			continue
+		}
+		h.Issuers++
+		if cn, on := cert.IssuerName.Key(); len(cert.IssuerName.RDNSequence) > 0 && (cn != "" || on != "") {
+			h.Parsed++
+		}
+	}
+	for _, invalid := range c.Invalid {
+		if invalid.Field == "issuerName" {
+			h.Issuers++
+		}
+	}
+	h.Rate = 1
+	if h.Issuers > 0 {
+		h.Rate = float64(h.Parsed) / float64(h.Issuers)
+	}
+	return h
+}
+
+// ErrNoIssuersParsed is returned by CheckIssuers when a collection has issuer names, but none parse.
+var ErrNoIssuersParsed = errors.New("none of the issuer names in the OneCRL collection could be parsed")
+
+// MaxUnparsedIssuers is the largest fraction of the issuer names of a collection that may fail to
+// parse before CheckIssuers takes it for a change in how Kinto encodes them, rather than for a
+// few corrupt records.
+var MaxUnparsedIssuers = 0.5
+
+// UnparsedIssuersError is returned by CheckIssuers when more than MaxUnparsedIssuers of the issuer
+// names of a collection fail to parse, though some do.
+type UnparsedIssuersError struct {
+	IssuerHealth
+	// The value of MaxUnparsedIssuers that was exceeded.
+	Max float64
+}
+
+func (e *UnparsedIssuersError) Error() string {
+	return fmt.Sprintf("%d of the %d issuer names in the OneCRL collection could not be parsed, more than the %g tolerated, "+
+		"which suggests that Kinto has changed how it encodes them rather than that a few records are corrupt",
+		e.Issuers-e.Parsed, e.Issuers, e.Max)
+}
+
+// CheckIssuers returns ErrNoIssuersParsed should the collection carry issuer names and yet not one
+// of them parse, or an UnparsedIssuersError should more than MaxUnparsedIssuers of them fail to.
+// Either would otherwise pass silently as a OneCRL that revokes less than it does.
+func (c *Collection) CheckIssuers() error {
+	h := c.IssuerHealth()
+	switch {
+	case h.Issuers > 0 && h.Parsed == 0:
+		return ErrNoIssuersParsed
+	case 1-h.Rate > MaxUnparsedIssuers:
+		return &UnparsedIssuersError{IssuerHealth: h, Max: MaxUnparsedIssuers}
+	}
+	return nil
+}
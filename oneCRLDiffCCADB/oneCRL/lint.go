@@ -0,0 +1,296 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"encoding/base64"
+	"fmt"
+	"sort"
+	"strings"
+)
+
+// The severities of a Finding.
+const (
+	SeverityError   = "error"
+	SeverityWarning = "warning"
+)
+
+// Finding is a single problem found in a revocations.txt.
+type Finding struct {
+	Severity string
+	Line     int
+	Message  string
+}
+
+// Lint is the outcome of checking a revocations.txt.
+type Lint struct {
+	Findings []Finding
+	// The number of distinct issuers and subjects, and of the valid revocations beneath them.
+	// A repeated revocation is counted once, and one that is reported as not valid base64
+	// or as beneath a block of the other form is not counted.
+	Issuers      int
+	Serials      int
+	Subjects     int
+	PubKeyHashes int
+	// The number of comments and blank lines, which are findings of their own only in strict mode.
+	Skipped Skipped
+	// The SHA-256 and length of the file that was checked.
+	Digest Digest
+}
+
+// LintRevocationsTxt checks a revocations.txt for lines that are not valid base64 or that contain
+// unexpected characters, revocations that precede any issuer or subject, repeated blocks, repeated
+// revocations beneath an issuer or subject, and trailing whitespace. A clean file has no findings.
+// Findings are ordered by line.
+//
+// In strict mode, comments and blank lines are warnings too, save for the generated header
+// with which Firefox's revocations.txt begins, as are a byte order mark and CRLF line endings,
+// which parsing tolerates.
+func LintRevocationsTxt(text string, strict bool) Lint {
+	l := Lint{Findings: make([]Finding, 0), Digest: DigestOf(text)}
+	report := func(severity string, line int, format string, args ...interface{}) {
+		l.Findings = append(l.Findings, Finding{Severity: severity, Line: line, Message: fmt.Sprintf(format, args...)})
+	}
+	// The line on which each block began.
+	blocks := make(map[string]int)
+	current := ""
+	kinds := make(map[string]string)
+	// The revocations that have been counted.
+	counted := make(map[position]bool)
+	// The last line that was scanned, so that a file that cannot be scanned to its end is
+	// reported on the line that follows it.
+	last := 0
+	if strict && strings.HasPrefix(text, byteOrderMark) {
+		report(SeverityWarning, 1, "byte order mark")
+	}
+	err := scanRevocationsTxt(strings.NewReader(text), func(line int, s string) error {
+		last = line
+		if trimmed := strings.TrimSuffix(s, "\r"); trimmed != s {
+			// A blank line is reported as such in strict mode, however it ends.
+			if strict && strings.TrimSpace(s) != "" {
+				report(SeverityWarning, line, "CRLF line ending")
+			}
+			s = trimmed
+		}
+		if trimmed := strings.TrimRight(s, " \t"); trimmed != s && strings.TrimSpace(s) != "" {
+			report(SeverityWarning, line, "trailing whitespace")
+			s = trimmed
+		}
+		switch {
+		case strings.TrimSpace(s) == "":
+			l.Skipped.BlankLines++
+			if strict {
+				report(SeverityWarning, line, "blank line")
+			}
+		case strings.HasPrefix(s, "#"):
+			l.Skipped.Comments++
+			if strict && !(line == 1 && s == generatedHeader) {
+				report(SeverityWarning, line, "comment")
+			}
+		case strings.HasPrefix(s, " "), strings.HasPrefix(s, "\t"):
+			kind, value := "serial", strings.TrimPrefix(s, " ")
+			if s[0] == '\t' {
+				kind, value = "public key hash", strings.TrimPrefix(s, "\t")
+			}
+			if current == "" {
+				report(SeverityError, line, "%s %q precedes any issuer or subject", kind, value)
+				return nil
+			}
+			valid := true
+			if problem := base64Problem(kind, value); problem != "" {
+				report(SeverityError, line, "%s", problem)
+				valid = false
+			}
+			if kinds[current] == "" {
+				kinds[current] = kind
+				if kind == "serial" {
+					l.Issuers++
+				} else {
+					l.Subjects++
+				}
+			} else if kinds[current] != kind {
+				report(SeverityError, line, "%s beneath a block of the other form, begun on line %d", kind, blocks[current])
+				valid = false
+			}
+			p := position{block: current, revocation: s[:1] + value}
+			if !valid || counted[p] {
+				return nil
+			}
+			counted[p] = true
+			if kind == "serial" {
+				l.Serials++
+			} else {
+				l.PubKeyHashes++
+			}
+		default:
+			if problem := base64Problem("issuer or subject", s); problem != "" {
+				report(SeverityError, line, "%s", problem)
+			}
+			if _, ok := blocks[s]; !ok {
+				blocks[s] = line
+			}
+			current = s
+		}
+		return nil
+	})
+	if err != nil {
+		report(SeverityError, last+1, "%s", err)
+	}
+	for _, d := range DuplicatesInRevocationsTxt(text) {
+		for _, line := range d.Lines[1:] {
+			if d.Revocation == "" {
+				report(SeverityWarning, line, "the block for %s begun on line %d is repeated", d.name(), d.Lines[0])
+			} else {
+				report(SeverityWarning, line, "%s %s beneath %s is repeated from line %d", d.Kind, d.Revocation, d.name(), d.Lines[0])
+			}
+		}
+	}
+	sort.SliceStable(l.Findings, func(i, j int) bool {
+		return l.Findings[i].Line < l.Findings[j].Line
+	})
+	return l
+}
+
+// base64Problem describes why the value is not valid base64, or is empty should it be valid.
+func base64Problem(kind, value string) string {
+	for _, r := range value {
+		if !(r >= 'A' && r <= 'Z' || r >= 'a' && r <= 'z' || r >= '0' && r <= '9' || r == '+' || r == '/' || r == '=') {
+			return fmt.Sprintf("%s contains the unexpected character %q", kind, r)
+		}
+	}
+	if _, err := base64.StdEncoding.DecodeString(value); err != nil {
+		return fmt.Sprintf("%s is not valid base64: %s", kind, err)
+	}
+	return ""
+}
+
+// Change is an edit that FixRevocationsTxt made to a revocations.txt.
+type Change struct {
+	// The line of the original file that was changed.
+	Line    int
+	Message string
+}
+
+// Fix is a revocations.txt with the problems that LintRevocationsTxt finds fixed.
+type Fix struct {
+	Text string
+	// What was changed to arrive at Text, ordered by line.
+	Changes []Change
+}
+
+// FixRevocationsTxt fixes the problems that LintRevocationsTxt finds in strict mode, so that
+// the fixed file may be committed. A byte order mark, CRLF line endings, and trailing whitespace
+// are stripped, comments (save for the generated header) and blank lines are dropped, repeated
+// blocks are merged into their first appearance, and repeated revocations are dropped. Lines that
+// cannot be fixed are dropped too: those that are not valid base64, revocations beneath a block of
+// the other form, and revocations that precede any issuer or subject or follow one that was
+// dropped. Otherwise the file keeps its order, as Revocations.Text renders it.
+func FixRevocationsTxt(text string) Fix {
+	f := Fix{Changes: make([]Change, 0)}
+	change := func(line int, format string, args ...interface{}) {
+		f.Changes = append(f.Changes, Change{Line: line, Message: fmt.Sprintf(format, args...)})
+	}
+	name := func(block string) string {
+		if cn := commonName(block); cn != "" {
+			return cn
+		}
+		return block
+	}
+	if strings.HasPrefix(text, byteOrderMark) {
+		change(1, "dropped the byte order mark")
+	}
+	r := newRevocations()
+	r.lines = make(map[position]int)
+	header := false
+	// The block that revocations fall beneath, or empty should there be none or should it have been dropped.
+	current := ""
+	// The line on which each block began, and the indentation of the revocations beneath it.
+	blocks := make(map[string]int)
+	indents := make(map[string]string)
+	last := 0
+	err := scanRevocationsTxt(strings.NewReader(text), func(line int, s string) error {
+		last = line
+		if trimmed := strings.TrimSuffix(s, "\r"); trimmed != s {
+			if strings.TrimSpace(s) != "" {
+				change(line, "replaced the CRLF line ending")
+			}
+			s = trimmed
+		}
+		if trimmed := strings.TrimRight(s, " \t"); trimmed != s && strings.TrimSpace(s) != "" {
+			change(line, "trimmed trailing whitespace")
+			s = trimmed
+		}
+		switch {
+		case strings.TrimSpace(s) == "":
+			change(line, "dropped a blank line")
+		case strings.HasPrefix(s, "#"):
+			if line == 1 && s == generatedHeader {
+				header = true
+			} else {
+				change(line, "dropped a comment")
+			}
+		case s[0] == ' ' || s[0] == '\t':
+			indent, kind, value := s[:1], "serial", s[1:]
+			if indent == "\t" {
+				kind = "public key hash"
+			}
+			if current == "" {
+				change(line, "dropped %s %s, which precedes any issuer or subject, or follows one that was dropped", kind, value)
+				return nil
+			}
+			if problem := base64Problem(kind, value); problem != "" {
+				change(line, "dropped the line, as its %s", problem)
+				return nil
+			}
+			if indents[current] == "" {
+				indents[current] = indent
+			} else if indents[current] != indent {
+				change(line, "dropped %s %s, which is beneath a block of the other form, begun on line %d", kind, value, blocks[current])
+				return nil
+			}
+			p := position{block: current, revocation: indent + value}
+			if first, ok := r.lines[p]; ok {
+				change(line, "dropped %s %s beneath %s, which is repeated from line %d", kind, value, name(current), first)
+				return nil
+			}
+			r.lines[p] = line
+			if indent == " " {
+				r.Serials[current] = append(r.Serials[current], value)
+			} else {
+				r.PubKeyHashes[current] = append(r.PubKeyHashes[current], value)
+			}
+		default:
+			if problem := base64Problem("issuer or subject", s); problem != "" {
+				change(line, "dropped the line, and every revocation beneath it, as its %s", problem)
+				current = ""
+				return nil
+			}
+			current = s
+			if first, ok := blocks[s]; ok {
+				change(line, "merged the block for %s into the one begun on line %d", name(s), first)
+			} else {
+				blocks[s] = line
+				r.lines[position{block: s}] = line
+			}
+		}
+		return nil
+	})
+	if err != nil {
+		change(last+1, "dropped the rest of the file, which could not be read: %s", err)
+	}
+	for block, line := range blocks {
+		if indents[block] == "" {
+			change(line, "dropped the block for %s, beneath which nothing is revoked", name(block))
+		}
+	}
+	sort.SliceStable(f.Changes, func(i, j int) bool {
+		return f.Changes[i].Line < f.Changes[j].Line
+	})
+	if header {
+		f.Text = generatedHeader + "\n"
+	}
+	f.Text += r.Text()
+	return f
+}
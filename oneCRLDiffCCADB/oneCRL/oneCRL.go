@@ -10,31 +10,171 @@ import (
 	"encoding/asn1"
 	"encoding/base64"
 	"encoding/json"
+	"errors"
 	"fmt"
-	"math/big"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/serial"
+	"io"
 	"net/http"
+	"net/url"
+	"strings"
+	"time"
 )
 
 const OneCRLEndpoint = "https://firefox.settings.services.mozilla.com/v1/buckets/blocklists/collections/certificates/records"
 
+// RetryPolicy governs how requests to Kinto that fail for transient reasons are retried.
+var RetryPolicy = fetch.DefaultPolicy.For("Kinto")
+
+// MaxResponseSize is the largest response body, in bytes, that will be read from Kinto. The
+// pages of a paginated collection share the one limit.
+var MaxResponseSize int64 = 32 << 20
+
+// MaxPages is the most pages of a paginated collection that will be read from Kinto.
+var MaxPages = 1000
+
+// Token, if set, is sent as a bearer token with requests to the hosts of the known Kinto
+// environments, some of whose buckets cannot be read without authentication. It is never
+// sent to any other host.
+var Token string
+
+// The Kinto environments that OneCRL may be retrieved from.
+const (
+	Production = "prod"
+	Stage      = "stage"
+	Preview    = "preview" // The production server's bucket of changes awaiting sign-off.
+)
+
+// environment is the OneCRL records endpoint of a Kinto environment, and the verifier
+// of the content signatures of its collections.
+type environment struct {
+	Endpoint string
+	Verifier Verifier
+}
+
+var environments = map[string]environment{
+	Production: {OneCRLEndpoint, DefaultVerifier},
+	Stage:      {"https://firefox.settings.services.allizom.org/v1/buckets/blocklists/collections/certificates/records", StageVerifier},
+	// The preview bucket is signed by the production signer, ahead of its changes being approved.
+	Preview: {"https://firefox.settings.services.mozilla.com/v1/buckets/blocklists-preview/collections/certificates/records", DefaultVerifier},
+}
+
+// Endpoint returns the OneCRL records endpoint of the given Kinto environment.
+// The empty string selects production.
+func Endpoint(env string) (string, error) {
+	if env == "" {
+		env = Production
+	}
+	e, ok := environments[env]
+	if !ok {
+		return "", fmt.Errorf("unknown Kinto environment %q", env)
+	}
+	return e.Endpoint, nil
+}
+
+// VerifierFor returns the verifier of the content signatures of the Kinto environment whose
+// records endpoint is the given URL. There is none for any other URL, since the root that
+// signs its collections is unknown.
+func VerifierFor(url string) (Verifier, bool) {
+	for _, e := range environments {
+		if e.Endpoint == url {
+			return e.Verifier, true
+		}
+	}
+	return Verifier{}, false
+}
+
+// Authenticates reports whether requests to the given URL carry the Token.
+func Authenticates(raw string) bool {
+	if Token == "" {
+		return false
+	}
+	u, err := url.Parse(raw)
+	if err != nil {
+		return false
+	}
+	for _, environment := range environments {
+		if e, err := url.Parse(environment.Endpoint); err == nil && e.Scheme == u.Scheme && e.Host == u.Host {
+			return true
+		}
+	}
+	return false
+}
+
+// AllowHTTP permits ParseURL to accept http URLs, such as those of a local development server.
+var AllowHTTP = false
+
+// ParseURL validates a caller supplied Kinto records URL, such as that of a development server,
+// returning it in its normalized form. Only https URLs (or http URLs, if AllowHTTP is set) of
+// the form .../buckets/{bucket}/collections/{collection}/records are accepted.
+func ParseURL(raw string) (string, error) {
+	u, err := url.Parse(raw)
+	if err != nil {
+		return "", err
+	}
+	if u.Scheme != "https" && !(AllowHTTP && u.Scheme == "http") {
+		return "", fmt.Errorf("the Kinto URL must use https, got %q", u.Scheme)
+	}
+	if u.Host == "" || u.User != nil || u.RawQuery != "" || u.Fragment != "" {
+		return "", fmt.Errorf("the Kinto URL must consist of only a host and a path, got %q", raw)
+	}
+	segments := strings.Split(strings.Trim(u.Path, "/"), "/")
+	n := len(segments)
+	if n < 5 || segments[n-5] != "buckets" || segments[n-3] != "collections" || segments[n-1] != "records" {
+		return "", fmt.Errorf("the Kinto URL must name the records of a collection, got %q", u.Path)
+	}
+	u.Path = "/" + strings.Join(segments, "/")
+	u.RawPath = ""
+	return u.String(), nil
+}
+
 type OneCRLIntermediates struct {
 	Data []*OneCRLIntermediate `json:"data"`
 }
 
 type OneCRLIntermediate struct {
-	Schema  int `json:"schema"`
-	Details struct {
-		Bug     string `json:"bug"`
-		Who     string `json:"who"`
-		Why     string `json:"why"`
-		Name    string `json:"name"`
-		Created string `json:"created"`
-	} `json:"details"`
-	Enabled      bool   `json:"enabled"`
-	IssuerName   Name   `json:"issuerName"`
-	SerialNumber string `json:"serialNumber"`
-	Id           string `json:"id"`
-	LastModified int    `json:"last_modified"`
+	Schema       int     `json:"schema"`
+	Details      Details `json:"details"`
+	Enabled      bool    `json:"enabled"`
+	IssuerName   Name    `json:"issuerName"`
+	SerialNumber string  `json:"serialNumber"`
+	Subject      string  `json:"subject,omitempty"`
+	PubKeyHash   string  `json:"pubKeyHash,omitempty"`
+	Id           string  `json:"id"`
+	LastModified int     `json:"last_modified"`
+	// The file that carries the record's data in place of the fields above, if any.
+	Attachment *Attachment `json:"attachment,omitempty"`
+}
+
+// UnmarshalJSON decodes a record, treating one that has no "enabled" flag as enabled. Not every
+// historic OneCRL record carries the flag, and such a record must not silently drop out of the
+// join. The absence of the flag is still counted among the SchemaWarnings.
+func (o *OneCRLIntermediate) UnmarshalJSON(raw []byte) error {
+	type plain OneCRLIntermediate
+	p := plain{Enabled: true}
+	err := json.Unmarshal(raw, &p)
+	*o = OneCRLIntermediate(p)
+	return err
+}
+
+// IsIssuerSerial reports whether the record revokes a certificate by its issuer and serial number.
+func (o *OneCRLIntermediate) IsIssuerSerial() bool {
+	return o.IssuerName.raw != "" && o.SerialNumber != ""
+}
+
+// IsSubjectKey reports whether the record revokes a certificate by its subject and
+// the hash of its public key. Such records cannot be matched against the CCADB report.
+func (o *OneCRLIntermediate) IsSubjectKey() bool {
+	return o.Subject != "" && o.PubKeyHash != ""
+}
+
+// Details is the human-oriented context that accompanies each OneCRL record.
+type Details struct {
+	Bug     string `json:"bug"`
+	Who     string `json:"who"`
+	Why     string `json:"why"`
+	Name    string `json:"name"`
+	Created string `json:"created"`
 }
 
 // Key constructs a string that is the concatenation of the certificate serial (decoded from base64 to an decimal value)
@@ -52,29 +192,496 @@ func (o *OneCRLIntermediate) Key() string {
 // The "key" in this case is the string concatenation of the decimal value of the certificate serial number,
 // the issuer common name, and the issuer organization name.
 func Retrieve() (map[string]*OneCRLIntermediate, error) {
-	result := make(map[string]*OneCRLIntermediate)
-	var intermediates OneCRLIntermediates
-	resp, err := http.DefaultClient.Get(OneCRLEndpoint)
+	return RetrieveFrom(OneCRLEndpoint)
+}
+
+// RetrieveFrom is Retrieve against an arbitrary Kinto records endpoint.
+func RetrieveFrom(url string) (map[string]*OneCRLIntermediate, error) {
+	c, err := RetrieveCollection(url)
+	if err != nil {
+		return make(map[string]*OneCRLIntermediate), err
+	}
+	return c.Intermediates(), nil
+}
+
+// Collection is a complete download of a OneCRL Kinto collection.
+type Collection struct {
+	// Every record in the collection, in the order that Kinto returned them.
+	Records []*OneCRLIntermediate
+	// The records that could not be decoded, and so are absent from Records.
+	Invalid []InvalidRecord
+	// The ETag of the collection, if Kinto provided one.
+	ETag string
+	// The collection timestamp, if the collection was retrieved from its changeset endpoint.
+	Timestamp int64
+	// The URL that the collection was retrieved from.
+	Source string
+	// When the collection was downloaded.
+	Retrieved time.Time
+	// The records that carry an attachment in place of a revocation, and so are absent from
+	// Records. Each attachment is verified, but Kinto does not define what it holds.
+	Unresolved []*OneCRLIntermediate
+	// How the records deviate from the fields that we expect of them.
+	Schema SchemaWarnings
+
+	// The records of a changeset exactly as they were received, along with
+	// its signature, so that its content signature may be verified.
+	signed    []json.RawMessage
+	signature *signatureMetadata
+}
+
+// Intermediates returns a mapping of "key"s to the issuer/serial records, as described by Retrieve.
+func (c *Collection) Intermediates() map[string]*OneCRLIntermediate {
+	result := make(map[string]*OneCRLIntermediate, len(c.Records))
+	for _, cert := range c.Records {
+		if cert.IsIssuerSerial() {
+			result[cert.Key()] = cert
+		}
+	}
+	return result
+}
+
+// EnabledIntermediates is Intermediates, less the records that are not enabled. A disabled
+// record is not published to Firefox, so is not expected to revoke anything.
+func (c *Collection) EnabledIntermediates() map[string]*OneCRLIntermediate {
+	result := c.Intermediates()
+	for key, cert := range result {
+		if !cert.Enabled {
+			delete(result, key)
+		}
+	}
+	return result
+}
+
+// Disabled returns the records that are not enabled.
+func (c *Collection) Disabled() []*OneCRLIntermediate {
+	records := make([]*OneCRLIntermediate, 0)
+	for _, cert := range c.Records {
+		if !cert.Enabled {
+			records = append(records, cert)
+		}
+	}
+	return records
+}
+
+// MissingBugReference returns the records whose details do not reference a bug, as policy
+// requires of every OneCRL entry.
+func (c *Collection) MissingBugReference() []*OneCRLIntermediate {
+	records := make([]*OneCRLIntermediate, 0)
+	for _, cert := range c.Records {
+		if strings.TrimSpace(cert.Details.Bug) == "" {
+			records = append(records, cert)
+		}
+	}
+	return records
+}
+
+// SubjectKeyRecords returns the records that revoke by subject and public key hash.
+func (c *Collection) SubjectKeyRecords() []*OneCRLIntermediate {
+	records := make([]*OneCRLIntermediate, 0)
+	for _, cert := range c.Records {
+		if !cert.IsIssuerSerial() && cert.IsSubjectKey() {
+			records = append(records, cert)
+		}
+	}
+	return records
+}
+
+// Unrecognized returns the records that have neither an issuer and serial number
+// nor a subject and public key hash.
+func (c *Collection) Unrecognized() []*OneCRLIntermediate {
+	records := make([]*OneCRLIntermediate, 0)
+	for _, cert := range c.Records {
+		if !cert.IsIssuerSerial() && !cert.IsSubjectKey() {
+			records = append(records, cert)
+		}
+	}
+	return records
+}
+
+// Duplicates returns every group of two or more records that share the same "key" (as described
+// by Retrieve), in the order that Kinto returned them. Only one record of each group can
+// participate in the join with the CCADB, so these are a data hygiene problem in their own right.
+func (c *Collection) Duplicates() [][]*OneCRLIntermediate {
+	groups := make(map[string][]*OneCRLIntermediate)
+	order := make([]string, 0)
+	for _, cert := range c.Records {
+		if !cert.IsIssuerSerial() {
+			continue
+		}
+		key := cert.Key()
+		if _, ok := groups[key]; !ok {
+			order = append(order, key)
+		}
+		groups[key] = append(groups[key], cert)
+	}
+	duplicates := make([][]*OneCRLIntermediate, 0)
+	for _, key := range order {
+		if len(groups[key]) > 1 {
+			duplicates = append(duplicates, groups[key])
+		}
+	}
+	return duplicates
+}
+
+// RetrieveCollection downloads every record of the Kinto collection at the given records endpoint.
+//
+// The collection's changeset endpoint is preferred, since it returns every record along with
+// the collection timestamp as one consistent snapshot. Should that fail, the records endpoint
+// is used instead. Kinto paginates large collections, so every page advertised via the
+// Next-Page response header is then followed until the collection is exhausted, provided
+// that the page is on the same host as the first, has not been read already, and is within
+// MaxPages.
+func RetrieveCollection(url string) (*Collection, error) {
+	return retrieveCollection(url, "")
+}
+
+// errNotModified is returned when Kinto reports that a collection has not
+// changed since the version identified by the ETag that was sent.
+var errNotModified = errors.New("the OneCRL collection has not been modified")
+
+// retrieveCollection is RetrieveCollection, except that if an ETag is given then the
+// collection is requested conditionally and errNotModified is returned if Kinto
+// reports that it is unchanged.
+func retrieveCollection(url, etag string) (*Collection, error) {
+	c, err := retrieveInline(url, etag)
 	if err != nil {
-		return result, err
+		return nil, err
+	}
+	if err := c.resolveAttachments(url); err != nil {
+		return nil, err
+	}
+	return c, nil
+}
+
+// retrieveInline is retrieveCollection, save that the records are left as Kinto returned them,
+// without verifying their attachments. The records endpoint is only fallen back upon should the
+// server have no changeset endpoint, or answer with something other than a changeset. Any other
+// failure, such as a timeout or being rate limited, would befall the records endpoint too.
+func retrieveInline(url, etag string) (*Collection, error) {
+	if strings.HasSuffix(url, "/records") {
+		changes := strings.TrimSuffix(url, "/records") + "/changeset?_expected=0"
+		c, err := retrieveChangeset(changes, etag)
+		var status *StatusError
+		var malformed *decodeError
+		missing := errors.As(err, &status) && status.StatusCode == http.StatusNotFound
+		if !missing && !errors.As(err, &malformed) {
+			return c, err
+		}
+	}
+	return retrieveRecords(url, etag)
+}
+
+func newCollection(source string) *Collection {
+	return &Collection{
+		Records:    make([]*OneCRLIntermediate, 0),
+		Invalid:    make([]InvalidRecord, 0),
+		Unresolved: make([]*OneCRLIntermediate, 0),
+		Source:     source,
+		Retrieved:  time.Now(),
+		Schema:     newSchemaWarnings(),
+	}
+}
+
+// add decodes the given record into the collection. Records are decoded one at
+// a time so that a single malformed record does not spoil the rest.
+func (c *Collection) add(raw json.RawMessage) {
+	c.Schema.check(raw)
+	cert, invalid := decodeRecord(raw)
+	if invalid != nil {
+		c.Invalid = append(c.Invalid, *invalid)
+		return
+	}
+	if cert.Attachment != nil && !cert.IsIssuerSerial() && !cert.IsSubjectKey() {
+		c.Unresolved = append(c.Unresolved, cert)
+		return
+	}
+	c.Records = append(c.Records, cert)
+}
+
+// changesetMetadata is the metadata of a Kinto changeset.
+type changesetMetadata struct {
+	// Newer servers may list several signatures instead, of which the first suffices.
+	Signature  *signatureMetadata   `json:"signature"`
+	Signatures []*signatureMetadata `json:"signatures"`
+}
+
+// retrieveChangeset reads the changeset endpoint, whose body is an object of
+// "metadata", "changes", and "timestamp".
+func retrieveChangeset(url, etag string) (*Collection, error) {
+	c := newCollection(url)
+	var metadata changesetMetadata
+	header, err := get(url, etag, func(d *json.Decoder) error {
+		return decodeObject(d, func(key string) error {
+			switch key {
+			case "metadata":
+				return d.Decode(&metadata)
+			case "timestamp":
+				return d.Decode(&c.Timestamp)
+			case "changes":
+				return decodeArray(d, func() error {
+					var raw json.RawMessage
+					if err := d.Decode(&raw); err != nil {
+						return err
+					}
+					c.signed = append(c.signed, raw)
+					c.add(raw)
+					return nil
+				})
+			}
+			return skip(d)
+		})
+	})
+	if err != nil {
+		return nil, err
+	}
+	c.ETag = header.Get("ETag")
+	c.signature = metadata.Signature
+	if c.signature == nil && len(metadata.Signatures) > 0 {
+		c.signature = metadata.Signatures[0]
+	}
+	return c, nil
+}
+
+// retrieveRecords reads the records endpoint, each page of whose body is an object of "data".
+func retrieveRecords(url, etag string) (*Collection, error) {
+	c := newCollection(url)
+	budget := MaxResponseSize
+	visited := make(map[string]bool)
+	for next := url; next != ""; {
+		if !sameOrigin(url, next) {
+			return nil, fmt.Errorf("the next page of %s is on another host, at %s", url, next)
+		}
+		if visited[next] {
+			return nil, fmt.Errorf("the pages of %s lead back to %s", url, next)
+		}
+		if len(visited) == MaxPages {
+			return nil, fmt.Errorf("the OneCRL collection at %s has more than %d pages", url, MaxPages)
+		}
+		visited[next] = true
+		header, err := getWithin(next, etag, &budget, func(d *json.Decoder) error {
+			return decodeObject(d, func(key string) error {
+				if key != "data" {
+					return skip(d)
+				}
+				return decodeArray(d, func() error {
+					var raw json.RawMessage
+					if err := d.Decode(&raw); err != nil {
+						return err
+					}
+					c.add(raw)
+					return nil
+				})
+			})
+		})
+		if err != nil {
+			return nil, err
+		}
+		if next == url {
+			c.ETag = header.Get("ETag")
+		}
+		// Only the first page is conditional.
+		etag = ""
+		next = header.Get("Next-Page")
+	}
+	return c, nil
+}
+
+// sameOrigin reports whether the two URLs share a scheme and host, so that a page of records
+// cannot send us to a host that the first URL did not name.
+func sameOrigin(first, next string) bool {
+	a, err := url.Parse(first)
+	if err != nil {
+		return false
+	}
+	b, err := url.Parse(next)
+	if err != nil {
+		return false
+	}
+	return a.Scheme == b.Scheme && a.Host == b.Host
+}
+
+// InvalidRecord is a OneCRL record that could not be decoded.
+type InvalidRecord struct {
+	Id string `json:"id"`
+	// The field at fault, if it is known.
+	Field string `json:"field"`
+	Error string `json:"error"`
+}
+
+// decodeRecord decodes a single Kinto record, returning an InvalidRecord instead if
+// the record is malformed or its issuerName or serialNumber are not valid base64.
+func decodeRecord(raw json.RawMessage) (*OneCRLIntermediate, *InvalidRecord) {
+	cert := new(OneCRLIntermediate)
+	field := ""
+	err := json.Unmarshal(raw, cert)
+	var nameErr *nameError
+	if errors.As(err, &nameErr) {
+		field = "issuerName"
+	}
+	if err == nil {
+		if _, err = base64.StdEncoding.DecodeString(cert.SerialNumber); err != nil {
+			field = "serialNumber"
+		}
+	}
+	if err == nil {
+		return cert, nil
+	}
+	var id struct {
+		Id string `json:"id"`
+	}
+	json.Unmarshal(raw, &id)
+	return nil, &InvalidRecord{Id: id.Id, Field: field, Error: err.Error()}
+}
+
+// StatusError is returned when Kinto answers a request with an unexpected status.
+type StatusError struct {
+	URL           string
+	StatusCode    int
+	Authenticated bool // Whether the request carried the Token.
+}
+
+func (e *StatusError) Error() string {
+	switch {
+	case (e.StatusCode == http.StatusUnauthorized || e.StatusCode == http.StatusForbidden) && e.Authenticated:
+		return fmt.Sprintf("Kinto rejected the credentials used to retrieve OneCRL from %s (status %d)", e.URL, e.StatusCode)
+	case e.StatusCode == http.StatusUnauthorized || e.StatusCode == http.StatusForbidden:
+		return fmt.Sprintf("Kinto requires credentials to retrieve OneCRL from %s (status %d)", e.URL, e.StatusCode)
+	case e.StatusCode == http.StatusNotFound:
+		return fmt.Sprintf("no OneCRL collection was found at %s (status %d)", e.URL, e.StatusCode)
+	}
+	return fmt.Sprintf("unexpected status %d when retrieving OneCRL from %s", e.StatusCode, e.URL)
+}
+
+// get hands a decoder of the JSON document at the given URL to decode, returning the response
+// headers (which carry, among other things, the URL of the next page of records). The document
+// is decoded as it arrives, rather than being buffered in full first, so that a large collection
+// is never held in memory both as raw JSON and as records.
+func get(url, etag string, decode func(*json.Decoder) error) (http.Header, error) {
+	budget := MaxResponseSize
+	return getWithin(url, etag, &budget, decode)
+}
+
+// getWithin is get, save that the document may be no larger than budget, from which what is
+// read of it is deducted. Reading several documents against the one budget bounds their total.
+func getWithin(url, etag string, budget *int64, decode func(*json.Decoder) error) (http.Header, error) {
+	req, err := http.NewRequest(http.MethodGet, url, nil)
+	if err != nil {
+		return nil, err
+	}
+	if etag != "" {
+		req.Header.Set("If-None-Match", etag)
+	}
+	authenticated := Authenticates(url)
+	if authenticated {
+		req.Header.Set("Authorization", "Bearer "+Token)
+	}
+	resp, err := RetryPolicy.Do(req)
+	if err != nil {
+		return nil, err
 	}
 	defer resp.Body.Close()
-	err = json.NewDecoder(resp.Body).Decode(&intermediates)
+	if resp.StatusCode == http.StatusNotModified && etag != "" {
+		return resp.Header, errNotModified
+	}
+	if resp.StatusCode != http.StatusOK {
+		return resp.Header, &StatusError{URL: url, StatusCode: resp.StatusCode, Authenticated: authenticated}
+	}
+	err = decode(json.NewDecoder(fetch.Limit(&countingReader{resp.Body, budget}, *budget)))
 	if err != nil {
-		return result, err
+		err = RetryPolicy.Wrap(url, err)
+		var timeout *fetch.TimeoutError
+		if errors.As(err, &timeout) || errors.Is(err, fetch.ErrTooLarge) {
+			return resp.Header, err
+		}
+		return resp.Header, &decodeError{err}
 	}
-	for _, cert := range intermediates.Data {
-		result[cert.Key()] = cert
+	return resp.Header, nil
+}
+
+// countingReader deducts the number of bytes that are read through it from n.
+type countingReader struct {
+	r io.Reader
+	n *int64
+}
+
+func (c *countingReader) Read(p []byte) (int, error) {
+	n, err := c.r.Read(p)
+	*c.n -= int64(n)
+	return n, err
+}
+
+// decodeError is returned when a Kinto response is not the document that was expected of it.
+type decodeError struct {
+	Err error
+}
+
+func (e *decodeError) Error() string {
+	return e.Err.Error()
+}
+
+func (e *decodeError) Unwrap() error {
+	return e.Err
+}
+
+// decodeObject walks the JSON object at the decoder's position, calling field with each of its
+// keys. field must consume the value of that key from the decoder in its entirety.
+func decodeObject(d *json.Decoder, field func(key string) error) error {
+	if err := expectDelim(d, '{'); err != nil {
+		return err
+	}
+	for d.More() {
+		t, err := d.Token()
+		if err != nil {
+			return err
+		}
+		key, _ := t.(string)
+		if err := field(key); err != nil {
+			return err
+		}
+	}
+	_, err := d.Token()
+	return err
+}
+
+// decodeArray calls element once for each element of the JSON array at the decoder's position.
+// element must consume that element from the decoder in its entirety.
+func decodeArray(d *json.Decoder, element func() error) error {
+	if err := expectDelim(d, '['); err != nil {
+		return err
+	}
+	for d.More() {
+		if err := element(); err != nil {
+			return err
+		}
+	}
+	_, err := d.Token()
+	return err
+}
+
+func expectDelim(d *json.Decoder, delim json.Delim) error {
+	t, err := d.Token()
+	if err != nil {
+		return err
+	}
+	if t != delim {
+		return fmt.Errorf("wanted %v in the Kinto response, got %v", delim, t)
 	}
-	return result, nil
+	return nil
+}
+
+// skip discards the JSON value at the decoder's position.
+func skip(d *json.Decoder) error {
+	var discard json.RawMessage
+	return d.Decode(&discard)
 }
 
 func (o *OneCRLIntermediate) decodeSerial() string {
-	s, err := base64.StdEncoding.DecodeString(o.SerialNumber)
+	s, err := serial.FromBase64(o.SerialNumber)
 	if err != nil {
 		panic(err)
 	}
-	return big.NewInt(0).SetBytes(s).String()
+	return s
 }
 
 // Name wraps a a vanilla RDN so that we can attach further methods for deserialization from JSON and extraction
@@ -82,6 +689,8 @@ func (o *OneCRLIntermediate) decodeSerial() string {
 type Name struct {
 	// https://tools.ietf.org/html/rfc5280#section-4.1.2.4
 	pkix.RDNSequence
+	// The base64 encoded DER exactly as it appeared in the record.
+	raw string
 }
 
 func (n *Name) Key() (string, string) {
@@ -102,18 +711,72 @@ func (n *Name) Key() (string, string) {
 	return cn, on
 }
 
+// Attribute is a single attribute of a distinguished name, such as its common name.
+type Attribute struct {
+	Type  string `json:"type"`
+	Value string `json:"value"`
+}
+
+// attributeTypes are the short names of the attribute types commonly found in the names of CAs.
+var attributeTypes = map[string]string{
+	"2.5.4.3":                    "CN",
+	"2.5.4.5":                    "serialNumber",
+	"2.5.4.6":                    "C",
+	"2.5.4.7":                    "L",
+	"2.5.4.8":                    "ST",
+	"2.5.4.9":                    "street",
+	"2.5.4.10":                   "O",
+	"2.5.4.11":                   "OU",
+	"2.5.4.17":                   "postalCode",
+	"2.5.4.97":                   "organizationIdentifier",
+	"0.9.2342.19200300.100.1.25": "DC",
+	"1.2.840.113549.1.9.1":       "emailAddress",
+}
+
+// Attributes lists every attribute of the name, in the order in which they are encoded. Key
+// considers only the common name and organization name, so two names that look alike there
+// may yet differ here. Types without a well known short name are given as their OID.
+func (n *Name) Attributes() []Attribute {
+	attributes := make([]Attribute, 0)
+	for _, i := range n.RDNSequence {
+		for _, j := range i {
+			t := j.Type.String()
+			if short, ok := attributeTypes[t]; ok {
+				t = short
+			}
+			attributes = append(attributes, Attribute{Type: t, Value: fmt.Sprint(j.Value)})
+		}
+	}
+	return attributes
+}
+
+// nameError is returned when an issuerName is not base64 encoded DER.
+type nameError struct {
+	error
+}
+
 func (n *Name) UnmarshalJSON(raw []byte) error {
 	// As it comes in, this buffer is just a JSON string, which
 	// includes double quotes that we do not want or need.
 	raw = bytes.Trim(raw, `"`)
+	n.raw = string(raw)
+	if len(raw) == 0 {
+		// Records that revoke by subject and key hash may carry an empty issuerName.
+		return nil
+	}
 	dst := make([]byte, len(raw))
 	_, err := base64.StdEncoding.Decode(dst, raw)
 	if err != nil {
-		return err
+		return &nameError{err}
 	}
 	_, err = asn1.Unmarshal(dst, &n.RDNSequence)
 	if err != nil {
-		return err
+		return &nameError{err}
 	}
 	return nil
 }
+
+// MarshalJSON emits the name exactly as it was received from Kinto.
+func (n Name) MarshalJSON() ([]byte, error) {
+	return json.Marshal(n.raw)
+}
@@ -0,0 +1,563 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"bufio"
+	"fmt"
+	"io"
+	"reflect"
+	"sort"
+	"strings"
+)
+
+// Revocations is the content of a file in the format of Firefox's revocations.txt.
+//
+// In that format, each issuer is written on a line of its own, followed by its serial numbers
+// each indented by a single space. Each subject is likewise followed by the hashes of its public
+// keys, each indented by a tab. Lines beginning with # are comments. Everything is base64.
+type Revocations struct {
+	// Serial numbers, keyed by issuer name.
+	Serials map[string][]string
+	// Public key hashes, keyed by subject.
+	PubKeyHashes map[string][]string
+	// The line on which each issuer and subject, and each revocation beneath it, first appears,
+	// should the revocations have been parsed from a file. They play no part in which revocations
+	// are present, and are carried over by Minus and Canonical.
+	lines map[position]int
+}
+
+// position identifies an issuer or subject of a revocations.txt, with an empty revocation, or a
+// revocation beneath one, indented as it is in the file so that a serial and a hash are told apart.
+type position struct {
+	block      string
+	revocation string
+}
+
+func newRevocations() *Revocations {
+	return &Revocations{Serials: make(map[string][]string), PubKeyHashes: make(map[string][]string)}
+}
+
+// Equal reports whether r and other hold the same revocations in the same order, regardless
+// of the lines on which either found them.
+func (r *Revocations) Equal(other *Revocations) bool {
+	return reflect.DeepEqual(r.Serials, other.Serials) && reflect.DeepEqual(r.PubKeyHashes, other.PubKeyHashes)
+}
+
+// Line returns the line, counting from one, on which the issuer or subject first appears, or
+// zero should the revocations not have been parsed from a file.
+func (r *Revocations) Line(block string) int {
+	return r.lines[position{block: block}]
+}
+
+// SerialLine returns the line on which the serial first appears beneath the issuer, or zero
+// should the revocations not have been parsed from a file.
+func (r *Revocations) SerialLine(issuer, serial string) int {
+	return r.lines[position{block: issuer, revocation: " " + serial}]
+}
+
+// PubKeyHashLine returns the line on which the public key hash first appears beneath the
+// subject, or zero should the revocations not have been parsed from a file.
+func (r *Revocations) PubKeyHashLine(subject, hash string) int {
+	return r.lines[position{block: subject, revocation: "\t" + hash}]
+}
+
+// Revocations collects the enabled records of the collection, as they would appear in revocations.txt.
+func (c *Collection) Revocations() *Revocations {
+	r := newRevocations()
+	for _, cert := range c.Records {
+		switch {
+		case !cert.Enabled:
+		case cert.IsIssuerSerial():
+			r.Serials[cert.IssuerName.raw] = append(r.Serials[cert.IssuerName.raw], cert.SerialNumber)
+		case cert.IsSubjectKey():
+			r.PubKeyHashes[cert.Subject] = append(r.PubKeyHashes[cert.Subject], cert.PubKeyHash)
+		}
+	}
+	return r
+}
+
+// RevocationsTxt renders the enabled records of the collection in the format of Firefox's
+// revocations.txt, so that it may be compared against a copy of that file with ordinary tools.
+func (c *Collection) RevocationsTxt() string {
+	return c.Revocations().String()
+}
+
+// SyntaxError is returned by ParseRevocationsTxt for a file that is not a valid revocations.txt,
+// as opposed to one that could not be read.
+type SyntaxError struct {
+	Line    int
+	Message string
+}
+
+func (e *SyntaxError) Error() string {
+	return fmt.Sprintf("line %d: %s", e.Line, e.Message)
+}
+
+// The comment with which Firefox's revocations.txt, and String, begin.
+const generatedHeader = "# Auto generated contents. Do not edit."
+
+// Skipped counts the lines of a revocations.txt that were passed over in parsing it.
+type Skipped struct {
+	Comments   int
+	BlankLines int
+}
+
+// ParseRevocationsTxt parses a file in the format of Firefox's revocations.txt. Issuer/serial
+// blocks and subject/hash blocks may be interleaved in any order.
+func ParseRevocationsTxt(text string) (*Revocations, error) {
+	r, _, err := ParseRevocationsTxtSkipping(text)
+	return r, err
+}
+
+// ParseRevocationsTxtSkipping is ParseRevocationsTxt, but also counts the comments and blank
+// lines that it skipped, as hand edited copies of the file tend to accumulate them.
+func ParseRevocationsTxtSkipping(text string) (*Revocations, Skipped, error) {
+	return ReadRevocationsTxt(strings.NewReader(text))
+}
+
+// ReadRevocationsTxt is ParseRevocationsTxtSkipping, reading the file line by line from r
+// rather than holding all of it in memory. Any error in reading r is returned as is, whereas
+// a file that is not a valid revocations.txt yields a SyntaxError.
+//
+// A byte order mark, CRLF line endings, and whitespace trailing a line are all tolerated, so
+// that a file that has passed through a Windows editor parses the same as the original. A first
+// issuer or subject that is not base64 is not, as the file is then likely something else entirely,
+// such as an HTML error page, which would otherwise parse as a file that revokes nothing.
+func ReadRevocationsTxt(reader io.Reader) (*Revocations, Skipped, error) {
+	r := newRevocations()
+	r.lines = make(map[position]int)
+	var skipped Skipped
+	current := ""
+	first := true
+	located := func(p position, line int) {
+		if _, ok := r.lines[p]; !ok {
+			r.lines[p] = line
+		}
+	}
+	err := scanRevocationsTxt(reader, func(line int, s string) error {
+		s = strings.TrimRight(s, "\r")
+		switch {
+		case strings.TrimSpace(s) == "":
+			skipped.BlankLines++
+		case strings.HasPrefix(s, "#"):
+			skipped.Comments++
+		case strings.HasPrefix(s, " "), strings.HasPrefix(s, "\t"):
+			if current == "" {
+				return &SyntaxError{Line: line, Message: "a revocation precedes any issuer or subject"}
+			}
+			revocation := strings.TrimSpace(s)
+			if s[0] == ' ' {
+				r.Serials[current] = append(r.Serials[current], revocation)
+			} else {
+				r.PubKeyHashes[current] = append(r.PubKeyHashes[current], revocation)
+			}
+			located(position{block: current, revocation: s[:1] + revocation}, line)
+		default:
+			current = strings.TrimSpace(s)
+			if first {
+				first = false
+				if problem := base64Problem("the first issuer or subject", current); problem != "" {
+					return &SyntaxError{Line: line, Message: problem + ", so this is not a revocations.txt"}
+				}
+			}
+			located(position{block: current}, line)
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, skipped, err
+	}
+	return r, skipped, nil
+}
+
+// The UTF-8 byte order mark, with which editors on Windows tend to begin a file.
+const byteOrderMark = "\ufeff"
+
+// scanRevocationsTxt calls f with each line of a revocations.txt, and its number counting from
+// one, until the file is exhausted or f returns an error. Any byte order mark is dropped from the
+// first line, but a carriage return that ends a line is left for f to deal with.
+func scanRevocationsTxt(r io.Reader, f func(line int, s string) error) error {
+	scanner := bufio.NewScanner(r)
+	scanner.Buffer(make([]byte, 0, 64*1024), 1024*1024)
+	for line := 1; scanner.Scan(); line++ {
+		s := scanner.Text()
+		if line == 1 {
+			s = strings.TrimPrefix(s, byteOrderMark)
+		}
+		if err := f(line, s); err != nil {
+			return err
+		}
+	}
+	return scanner.Err()
+}
+
+// Duplicate is a block of a revocations.txt, or a revocation beneath one, that the file lists more than once.
+type Duplicate struct {
+	// "serial" or "public key hash", or "issuer or subject" if it is the block itself that is repeated.
+	Kind string
+	// The issuer or subject, and its common name should it parse as a distinguished name.
+	Block      string
+	CommonName string
+	// The serial or public key hash, or empty if it is the block that is repeated.
+	Revocation string `json:",omitempty"`
+	// The lines on which it appears, in order.
+	Lines []int
+}
+
+// name is how findings refer to the block of the duplicate.
+func (d Duplicate) name() string {
+	if d.CommonName != "" {
+		return d.CommonName
+	}
+	return d.Block
+}
+
+// DuplicatesInRevocationsTxt finds the blocks of a revocations.txt that appear more than once, and
+// the revocations that appear more than once beneath the same issuer or subject, whether within
+// one instance of its block or across several. Parsing the file into Revocations hides both.
+// Revocations that precede any issuer or subject are not considered.
+func DuplicatesInRevocationsTxt(text string) []Duplicate {
+	type revocation struct {
+		block string
+		// The revocation with its indentation, so that a serial and a hash are told apart.
+		line string
+	}
+	blocks := make(map[string][]int)
+	revocations := make(map[revocation][]int)
+	current := ""
+	// A file that cannot be scanned to its end is reported by whatever parses it.
+	scanRevocationsTxt(strings.NewReader(text), func(line int, s string) error {
+		s = strings.TrimRight(s, " \t\r")
+		switch {
+		case s == "" || strings.HasPrefix(s, "#"):
+		case s[0] == ' ' || s[0] == '\t':
+			if current != "" {
+				r := revocation{block: current, line: s}
+				revocations[r] = append(revocations[r], line)
+			}
+		default:
+			current = s
+			blocks[s] = append(blocks[s], line)
+		}
+		return nil
+	})
+	duplicates := make([]Duplicate, 0)
+	for block, lines := range blocks {
+		if len(lines) > 1 {
+			duplicates = append(duplicates, Duplicate{Kind: "issuer or subject", Block: block, CommonName: commonName(block), Lines: lines})
+		}
+	}
+	for r, lines := range revocations {
+		if len(lines) > 1 {
+			kind := "serial"
+			if r.line[0] == '\t' {
+				kind = "public key hash"
+			}
+			duplicates = append(duplicates, Duplicate{Kind: kind, Block: r.block, CommonName: commonName(r.block), Revocation: r.line[1:], Lines: lines})
+		}
+	}
+	// No two duplicates begin on the same line.
+	sort.Slice(duplicates, func(i, j int) bool {
+		return duplicates[i].Lines[0] < duplicates[j].Lines[0]
+	})
+	return duplicates
+}
+
+// commonName returns the common name of a base64 encoded distinguished name, or the
+// empty string should it not be one.
+func commonName(encoded string) string {
+	cn, _ := parseName(encoded)
+	return cn
+}
+
+// parseName returns the common name and organization name of a base64 encoded
+// distinguished name, or empty strings should it not be one.
+func parseName(encoded string) (string, string) {
+	n := new(Name)
+	if err := n.UnmarshalJSON([]byte(encoded)); err != nil {
+		return "", ""
+	}
+	return n.Key()
+}
+
+// Block is the revocations beneath a single issuer or subject of a revocations.txt.
+type Block struct {
+	// The issuer or subject, as it is written in the file, and the names parsed from it
+	// should it be a distinguished name.
+	Name         string
+	CommonName   string
+	Organization string
+	// The serials or public key hashes, sorted.
+	Revocations []string
+	// The line on which the issuer or subject first appears, and on which each of its revocations
+	// first appears, in the order of Revocations. Only the ...WithLines methods set them.
+	Line            int   `json:",omitempty"`
+	RevocationLines []int `json:",omitempty"`
+}
+
+// SerialBlocks returns the serials beneath each issuer, ordered by issuer.
+func (r *Revocations) SerialBlocks() []Block {
+	return r.blocks(r.Serials, "", false)
+}
+
+// PubKeyHashBlocks returns the public key hashes beneath each subject, ordered by subject.
+func (r *Revocations) PubKeyHashBlocks() []Block {
+	return r.blocks(r.PubKeyHashes, "", false)
+}
+
+// SerialBlocksWithLines is SerialBlocks, but also gives the lines of the file on which each issuer
+// and serial first appear, or zero should the revocations not have been parsed from a file.
+func (r *Revocations) SerialBlocksWithLines() []Block {
+	return r.blocks(r.Serials, " ", true)
+}
+
+// PubKeyHashBlocksWithLines is PubKeyHashBlocks, but also gives the lines of the file on which each
+// subject and public key hash first appear, or zero should the revocations not have been parsed from a file.
+func (r *Revocations) PubKeyHashBlocksWithLines() []Block {
+	return r.blocks(r.PubKeyHashes, "\t", true)
+}
+
+func (r *Revocations) blocks(entries map[string][]string, indent string, withLines bool) []Block {
+	result := make([]Block, 0, len(entries))
+	for name, revocations := range entries {
+		cn, org := parseName(name)
+		sorted := append([]string(nil), revocations...)
+		sort.Strings(sorted)
+		b := Block{Name: name, CommonName: cn, Organization: org, Revocations: sorted}
+		if withLines {
+			b.Line = r.lines[position{block: name}]
+			b.RevocationLines = make([]int, len(sorted))
+			for i, revocation := range sorted {
+				b.RevocationLines[i] = r.lines[position{block: name, revocation: indent + revocation}]
+			}
+		}
+		result = append(result, b)
+	}
+	sort.Slice(result, func(i, j int) bool {
+		return result[i].Name < result[j].Name
+	})
+	return result
+}
+
+// Counts is the number of distinct issuers and subjects in revocations, and of the revocations beneath them.
+type Counts struct {
+	Issuers      int
+	Serials      int
+	Subjects     int
+	PubKeyHashes int
+}
+
+// Counts counts the revocations, ignoring any that are repeated.
+func (r *Revocations) Counts() Counts {
+	return Counts{
+		Issuers:      len(r.Serials),
+		Serials:      distinct(r.Serials),
+		Subjects:     len(r.PubKeyHashes),
+		PubKeyHashes: distinct(r.PubKeyHashes),
+	}
+}
+
+func distinct(entries map[string][]string) int {
+	n := 0
+	for _, values := range entries {
+		n += distinctValues(values)
+	}
+	return n
+}
+
+func distinctValues(values []string) int {
+	seen := make(map[string]bool, len(values))
+	for _, value := range values {
+		seen[value] = true
+	}
+	return len(seen)
+}
+
+// RevokedSerial is a serial number and the issuer beneath which it is revoked, both base64 as
+// Firefox stores them.
+type RevokedSerial struct {
+	Issuer string
+	Serial string
+}
+
+// RevokedSerials lists each distinct pair of issuer and serial number of the revocations, sorted
+// by issuer and then by serial, for those who want the whole of what is revoked rather than a diff.
+func (r *Revocations) RevokedSerials() []RevokedSerial {
+	pairs := make([]RevokedSerial, 0, distinct(r.Serials))
+	for issuer, serials := range r.Canonical().Serials {
+		for _, serial := range serials {
+			pairs = append(pairs, RevokedSerial{Issuer: issuer, Serial: serial})
+		}
+	}
+	sort.Slice(pairs, func(i, j int) bool {
+		if pairs[i].Issuer != pairs[j].Issuer {
+			return pairs[i].Issuer < pairs[j].Issuer
+		}
+		return pairs[i].Serial < pairs[j].Serial
+	})
+	return pairs
+}
+
+// LargestIssuers is how many of the issuers with the most serials Stats lists.
+const LargestIssuers = 10
+
+// Stats summarizes revocations for reporting.
+type Stats struct {
+	Counts
+	// The issuers with the most serials, largest first, and at most LargestIssuers of them.
+	LargestIssuers []IssuerSerials
+}
+
+// IssuerSerials is the number of distinct serials beneath an issuer.
+type IssuerSerials struct {
+	Issuer     string
+	CommonName string
+	Serials    int
+}
+
+// Stats counts the revocations, as Counts does, and finds the issuers with the most serials.
+func (r *Revocations) Stats() Stats {
+	issuers := make([]IssuerSerials, 0, len(r.Serials))
+	for issuer, serials := range r.Serials {
+		issuers = append(issuers, IssuerSerials{Issuer: issuer, Serials: distinctValues(serials)})
+	}
+	sort.Slice(issuers, func(i, j int) bool {
+		if issuers[i].Serials != issuers[j].Serials {
+			return issuers[i].Serials > issuers[j].Serials
+		}
+		return issuers[i].Issuer < issuers[j].Issuer
+	})
+	if len(issuers) > LargestIssuers {
+		issuers = issuers[:LargestIssuers]
+	}
+	// Only the issuers that are listed have their names parsed.
+	for i := range issuers {
+		issuers[i].CommonName = commonName(issuers[i].Issuer)
+	}
+	return Stats{Counts: r.Counts(), LargestIssuers: issuers}
+}
+
+// Minus returns the revocations, of both forms, that are present in r but absent from other.
+func (r *Revocations) Minus(other *Revocations) *Revocations {
+	result := newRevocations()
+	result.Serials = minus(r.Serials, other.Serials)
+	result.PubKeyHashes = minus(r.PubKeyHashes, other.PubKeyHashes)
+	result.lines = r.lines
+	return result
+}
+
+func minus(a, b map[string][]string) map[string][]string {
+	result := make(map[string][]string)
+	for key, values := range a {
+		present := make(map[string]bool, len(b[key]))
+		for _, value := range b[key] {
+			present[value] = true
+		}
+		for _, value := range values {
+			if !present[value] {
+				result[key] = append(result[key], value)
+			}
+		}
+	}
+	return result
+}
+
+// Canonical returns a copy of the revocations in canonical form: the revocations beneath each
+// issuer and subject sorted, and each written once. Two files that differ only in the order and
+// repetition of their revocations, their comments, and their whitespace, have the same canonical form.
+func (r *Revocations) Canonical() *Revocations {
+	result := newRevocations()
+	result.Serials = canonical(r.Serials)
+	result.PubKeyHashes = canonical(r.PubKeyHashes)
+	result.lines = r.lines
+	return result
+}
+
+func canonical(entries map[string][]string) map[string][]string {
+	result := make(map[string][]string, len(entries))
+	for key, values := range entries {
+		sorted := append([]string(nil), values...)
+		sort.Strings(sorted)
+		for i, value := range sorted {
+			if i == 0 || value != sorted[i-1] {
+				result[key] = append(result[key], value)
+			}
+		}
+	}
+	return result
+}
+
+// String renders the revocations canonically in the format of revocations.txt: issuers, and
+// then subjects, sorted by their base64, each followed by its revocations as Canonical orders
+// them, with every line ending in a newline and bearing no trailing whitespace.
+func (r *Revocations) String() string {
+	c := r.Canonical()
+	b := new(strings.Builder)
+	b.WriteString(generatedHeader + "\n")
+	writeRevocations(b, c.Serials, " ")
+	writeRevocations(b, c.PubKeyHashes, "\t")
+	return b.String()
+}
+
+func writeRevocations(b *strings.Builder, entries map[string][]string, indent string) {
+	keys := make([]string, 0, len(entries))
+	for key := range entries {
+		keys = append(keys, key)
+	}
+	sort.Strings(keys)
+	for _, key := range keys {
+		b.WriteString(key + "\n")
+		for _, value := range entries[key] {
+			b.WriteString(indent + value + "\n")
+		}
+	}
+}
+
+// Text renders the revocations in the format of revocations.txt in the order in which they were
+// parsed, rather than canonically: each issuer and subject where it first appears in the file,
+// followed by its revocations in the order in which they first appear, each written once. A block
+// that the file repeats is merged into its first appearance. Blocks whose lines are not known,
+// such as those of OneCRL, follow in the order of their base64. Comments and blank lines are not kept.
+func (r *Revocations) Text() string {
+	type textBlock struct {
+		name        string
+		indent      string
+		revocations []string
+		line        int
+	}
+	blocks := make([]textBlock, 0, len(r.Serials)+len(r.PubKeyHashes))
+	for name, serials := range r.Serials {
+		blocks = append(blocks, textBlock{name, " ", serials, r.Line(name)})
+	}
+	for name, hashes := range r.PubKeyHashes {
+		blocks = append(blocks, textBlock{name, "\t", hashes, r.Line(name)})
+	}
+	sort.Slice(blocks, func(i, j int) bool {
+		a, b := blocks[i], blocks[j]
+		switch {
+		case a.line != b.line && (a.line == 0 || b.line == 0):
+			return b.line == 0
+		case a.line != b.line:
+			return a.line < b.line
+		case a.name != b.name:
+			return a.name < b.name
+		}
+		// The serials and hashes of a name that is both an issuer and a subject.
+		return a.indent == " "
+	})
+	b := new(strings.Builder)
+	for _, block := range blocks {
+		b.WriteString(block.name + "\n")
+		seen := make(map[string]bool, len(block.revocations))
+		for _, revocation := range block.revocations {
+			if !seen[revocation] {
+				seen[revocation] = true
+				b.WriteString(block.indent + revocation + "\n")
+			}
+		}
+	}
+	return b.String()
+}
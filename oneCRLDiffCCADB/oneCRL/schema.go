@@ -0,0 +1,57 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"encoding/json"
+	"reflect"
+	"strings"
+)
+
+// SchemaWarnings summarizes how the records of a collection deviate from the fields that we expect
+// of them. Unknown fields are otherwise ignored, so this is the first sign of the Remote Settings
+// schema having changed underneath us.
+type SchemaWarnings struct {
+	// The number of records bearing each field that OneCRLIntermediate does not know of.
+	Unexpected map[string]int
+	// The number of records lacking each field that we rely upon.
+	Missing map[string]int
+}
+
+func newSchemaWarnings() SchemaWarnings {
+	return SchemaWarnings{Unexpected: make(map[string]int), Missing: make(map[string]int)}
+}
+
+// The fields that every record is expected to carry, regardless of how it revokes.
+var requiredFields = []string{"id", "last_modified", "enabled", "details"}
+
+// knownFields are the JSON names of the fields of OneCRLIntermediate.
+var knownFields = func() map[string]bool {
+	known := make(map[string]bool)
+	t := reflect.TypeOf(OneCRLIntermediate{})
+	for i := 0; i < t.NumField(); i++ {
+		name := strings.Split(t.Field(i).Tag.Get("json"), ",")[0]
+		known[name] = true
+	}
+	return known
+}()
+
+// check tallies the deviations of a single raw record.
+func (s SchemaWarnings) check(raw json.RawMessage) {
+	var fields map[string]json.RawMessage
+	if json.Unmarshal(raw, &fields) != nil {
+		return
+	}
+	for name := range fields {
+		if !knownFields[name] {
+			s.Unexpected[name]++
+		}
+	}
+	for _, name := range requiredFields {
+		if _, ok := fields[name]; !ok {
+			s.Missing[name]++
+		}
+	}
+}
@@ -0,0 +1,272 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"bytes"
+	"crypto/ecdsa"
+	"crypto/sha256"
+	"crypto/sha512"
+	"crypto/x509"
+	"encoding/base64"
+	"encoding/hex"
+	"encoding/json"
+	"encoding/pem"
+	"errors"
+	"fmt"
+	"github.com/mozilla/CCADB-Tools/oneCRLDiffCCADB/fetch"
+	"io"
+	"math/big"
+	"net/http"
+	"sort"
+	"strconv"
+	"strings"
+	"unicode/utf16"
+)
+
+// Verifier checks the Remote Settings content signature of a collection in the same manner as Firefox.
+// https://firefox-source-docs.mozilla.org/security/nss/legacy/content_signature/index.html
+type Verifier struct {
+	// The SHA-256 fingerprint of the root of the signing certificate chain.
+	RootHash [sha256.Size]byte
+	// The DNS name that the signing certificate must be issued to.
+	Signer string
+}
+
+// DefaultVerifier verifies collections signed by the production Remote Settings signer for OneCRL.
+var DefaultVerifier = Verifier{
+	RootHash: mustParseHash("97:E8:BA:9C:F1:2F:B3:DE:53:CC:42:A4:E6:57:7E:D6:4D:F4:93:C2:47:B4:14:FE:A0:36:81:8D:38:23:56:0E"),
+	Signer:   "onecrl.content-signature.mozilla.org",
+}
+
+// StageVerifier verifies collections signed by the stage Remote Settings signer for OneCRL,
+// whose chain terminates in the stage root rather than the production one.
+var StageVerifier = Verifier{
+	RootHash: mustParseHash("3C:01:44:6A:BE:90:36:CE:A9:A0:9A:CA:A3:A5:20:AC:62:8F:20:A7:AE:32:CE:86:1C:B2:EF:B7:0F:A0:C7:45"),
+	Signer:   "onecrl.content-signature.mozilla.org",
+}
+
+// mustParseHash parses a colon separated hex fingerprint, as Firefox writes them in its preferences.
+func mustParseHash(fingerprint string) [sha256.Size]byte {
+	var hash [sha256.Size]byte
+	b, err := hex.DecodeString(strings.ReplaceAll(fingerprint, ":", ""))
+	if err != nil || len(b) != len(hash) {
+		panic(fmt.Sprintf("invalid SHA-256 fingerprint %q", fingerprint))
+	}
+	copy(hash[:], b)
+	return hash
+}
+
+// signatureMetadata is the portion of a changeset's metadata that describes its content signature.
+type signatureMetadata struct {
+	X5U       string `json:"x5u"`
+	Signature string `json:"signature"`
+}
+
+// VerifySignature verifies the content signature of the collection, which is
+// only available if the collection was retrieved from its changeset endpoint.
+func (c *Collection) VerifySignature(v Verifier) error {
+	if c.signature == nil {
+		return errors.New("the OneCRL collection carries no content signature")
+	}
+	payload, err := canonicalPayload(c.signed, c.Timestamp)
+	if err != nil {
+		return err
+	}
+	chain, err := retrieveChain(c.signature.X5U)
+	if err != nil {
+		return err
+	}
+	leaf, err := v.verifyChain(chain)
+	if err != nil {
+		return err
+	}
+	key, ok := leaf.PublicKey.(*ecdsa.PublicKey)
+	if !ok {
+		return fmt.Errorf("the content signing certificate has a %T public key, wanted ECDSA", leaf.PublicKey)
+	}
+	signature, err := base64.RawURLEncoding.DecodeString(strings.TrimRight(c.signature.Signature, "="))
+	if err != nil {
+		return fmt.Errorf("the content signature is not valid base64: %s", err)
+	}
+	if len(signature)%2 != 0 {
+		return fmt.Errorf("the content signature has an unexpected length of %d", len(signature))
+	}
+	r := new(big.Int).SetBytes(signature[:len(signature)/2])
+	s := new(big.Int).SetBytes(signature[len(signature)/2:])
+	digest := sha512.Sum384(append([]byte("Content-Signature:\x00"), payload...))
+	if !ecdsa.Verify(key, digest[:], r, s) {
+		return errors.New("the content signature of the OneCRL collection does not match its records")
+	}
+	return nil
+}
+
+// retrieveChain downloads the PEM encoded certificate chain located at the x5u URL.
+func retrieveChain(x5u string) ([]*x509.Certificate, error) {
+	req, err := http.NewRequest(http.MethodGet, x5u, nil)
+	if err != nil {
+		return nil, err
+	}
+	resp, err := RetryPolicy.Do(req)
+	if err != nil {
+		return nil, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return nil, fmt.Errorf("unexpected status %d when retrieving the content signing chain from %s", resp.StatusCode, x5u)
+	}
+	raw, err := io.ReadAll(fetch.Limit(resp.Body, MaxResponseSize))
+	if err != nil {
+		return nil, RetryPolicy.Wrap(x5u, err)
+	}
+	chain := make([]*x509.Certificate, 0)
+	for block, rest := pem.Decode(raw); block != nil; block, rest = pem.Decode(rest) {
+		cert, err := x509.ParseCertificate(block.Bytes)
+		if err != nil {
+			return nil, err
+		}
+		chain = append(chain, cert)
+	}
+	if len(chain) < 2 {
+		return nil, fmt.Errorf("wanted a content signing chain of at least two certificates from %s, got %d", x5u, len(chain))
+	}
+	return chain, nil
+}
+
+// verifyChain checks that the chain (ordered from leaf to root) terminates in the expected root
+// and that its leaf is a currently valid code signing certificate issued to the expected signer.
+func (v Verifier) verifyChain(chain []*x509.Certificate) (*x509.Certificate, error) {
+	root := chain[len(chain)-1]
+	if sha256.Sum256(root.Raw) != v.RootHash {
+		return nil, errors.New("the content signing chain does not terminate in the expected root")
+	}
+	roots := x509.NewCertPool()
+	roots.AddCert(root)
+	intermediates := x509.NewCertPool()
+	for _, cert := range chain[1 : len(chain)-1] {
+		intermediates.AddCert(cert)
+	}
+	leaf := chain[0]
+	_, err := leaf.Verify(x509.VerifyOptions{
+		DNSName:       v.Signer,
+		Roots:         roots,
+		Intermediates: intermediates,
+		KeyUsages:     []x509.ExtKeyUsage{x509.ExtKeyUsageCodeSigning},
+	})
+	if err != nil {
+		return nil, err
+	}
+	return leaf, nil
+}
+
+// canonicalPayload serializes the records and timestamp of a collection in the canonical
+// form that Remote Settings signs: the non-deleted records sorted by id, with every object
+// key sorted, no insignificant whitespace, and all non-ASCII characters escaped.
+func canonicalPayload(records []json.RawMessage, timestamp int64) ([]byte, error) {
+	data := make([]interface{}, 0, len(records))
+	for _, raw := range records {
+		decoder := json.NewDecoder(bytes.NewReader(raw))
+		decoder.UseNumber()
+		var record map[string]interface{}
+		if err := decoder.Decode(&record); err != nil {
+			return nil, err
+		}
+		if deleted, _ := record["deleted"].(bool); deleted {
+			continue
+		}
+		data = append(data, record)
+	}
+	sort.SliceStable(data, func(i, j int) bool {
+		a, _ := data[i].(map[string]interface{})["id"].(string)
+		b, _ := data[j].(map[string]interface{})["id"].(string)
+		return a < b
+	})
+	buf := new(bytes.Buffer)
+	err := writeCanonical(buf, map[string]interface{}{
+		"data":          data,
+		"last_modified": strconv.FormatInt(timestamp, 10),
+	})
+	return buf.Bytes(), err
+}
+
+func writeCanonical(buf *bytes.Buffer, v interface{}) error {
+	switch t := v.(type) {
+	case nil:
+		buf.WriteString("null")
+	case bool:
+		buf.WriteString(strconv.FormatBool(t))
+	case json.Number:
+		buf.WriteString(t.String())
+	case string:
+		writeCanonicalString(buf, t)
+	case []interface{}:
+		buf.WriteByte('[')
+		for i, e := range t {
+			if i > 0 {
+				buf.WriteByte(',')
+			}
+			if err := writeCanonical(buf, e); err != nil {
+				return err
+			}
+		}
+		buf.WriteByte(']')
+	case map[string]interface{}:
+		keys := make([]string, 0, len(t))
+		for k := range t {
+			keys = append(keys, k)
+		}
+		sort.Strings(keys)
+		buf.WriteByte('{')
+		for i, k := range keys {
+			if i > 0 {
+				buf.WriteByte(',')
+			}
+			writeCanonicalString(buf, k)
+			buf.WriteByte(':')
+			if err := writeCanonical(buf, t[k]); err != nil {
+				return err
+			}
+		}
+		buf.WriteByte('}')
+	default:
+		return fmt.Errorf("cannot canonicalize a value of type %T", v)
+	}
+	return nil
+}
+
+// writeCanonicalString writes a string as Remote Settings and Firefox's CanonicalJSON do, which
+// write only printable ASCII literally. DEL (0x7f) is not printable, so is escaped along with the
+// other control characters and everything beyond ASCII, as \u007f.
+func writeCanonicalString(buf *bytes.Buffer, s string) {
+	buf.WriteByte('"')
+	for _, r := range s {
+		switch r {
+		case '"':
+			buf.WriteString(`\"`)
+		case '\\':
+			buf.WriteString(`\\`)
+		case '\n':
+			buf.WriteString(`\n`)
+		case '\r':
+			buf.WriteString(`\r`)
+		case '\t':
+			buf.WriteString(`\t`)
+		case '\b':
+			buf.WriteString(`\b`)
+		case '\f':
+			buf.WriteString(`\f`)
+		default:
+			if r >= 0x20 && r <= 0x7e {
+				buf.WriteRune(r)
+			} else if r > 0xFFFF {
+				r1, r2 := utf16.EncodeRune(r)
+				fmt.Fprintf(buf, `\u%04x\u%04x`, r1, r2)
+			} else {
+				fmt.Fprintf(buf, `\u%04x`, r)
+			}
+		}
+	}
+	buf.WriteByte('"')
+}
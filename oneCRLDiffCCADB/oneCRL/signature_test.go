@@ -0,0 +1,203 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package oneCRL
+
+import (
+	"bytes"
+	"encoding/json"
+	"fmt"
+	"net/http"
+	"net/http/httptest"
+	"strings"
+	"testing"
+)
+
+// The fixtures below were produced by signing signedPayload with the leaf of signingChain,
+// a throwaway chain whose root has the fingerprint in testVerifier.
+
+var testVerifier = Verifier{
+	RootHash: mustParseHash("5833702f72843f37d90ffaf018c3e82c97180d16b925a62f0b5ad2de0bd26415"),
+	Signer:   "onecrl.content-signature.mozilla.org",
+}
+
+const signedTimestamp = 1700000000002
+
+const signedSignature = "8AahurPb6-1Efus3dlLd0YJzgplZpLVFkoFnK-HwQfkN3ZWaNRV5BoMA3n9FSbr9hXa70tpUEbecfV8XXlQ2i1IEVyH0UozHShFZvgpn37-o_rjvoTDW5HgIwia8DKic"
+
+const signedChanges = `[
+ {
+  "id": "b-record",
+  "last_modified": 1700000000002,
+  "enabled": true,
+  "issuerName": "MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E=",
+  "serialNumber": "AQIDBAY=",
+  "details": {
+   "bug": "https://bugzilla.mozilla.org/2",
+   "name": "Café ✓ <b>",
+   "why": "line\nbreak",
+   "who": "",
+   "created": ""
+  },
+  "schema": 1
+ },
+ {
+  "id": "a-record",
+  "last_modified": 1700000000001,
+  "enabled": true,
+  "issuerName": "MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E=",
+  "serialNumber": "AQIDBAU=",
+  "details": {
+   "bug": "https://bugzilla.mozilla.org/1",
+   "name": "𝄞",
+   "why": "",
+   "who": "",
+   "created": ""
+  },
+  "schema": 1
+ }
+]`
+
+const signedPayload = `{"data":[{"details":{"bug":"https://bugzilla.mozilla.org/1","created":"","name":"\ud834\udd1e","who":"","why":""},"enabled":true,"id":"a-record","issuerName":"MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E=","last_modified":1700000000001,"schema":1,"serialNumber":"AQIDBAU="},{"details":{"bug":"https://bugzilla.mozilla.org/2","created":"","name":"Caf\u00e9 \u2713 <b>","who":"","why":"line\nbreak"},"enabled":true,"id":"b-record","issuerName":"MEUxCzAJBgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEgMB4GA1UEAwwXRXhhbXBsZSBJbnRlcm1lZGlhdGUgQ0E=","last_modified":1700000000002,"schema":1,"serialNumber":"AQIDBAY="}],"last_modified":"1700000000002"}`
+
+const signingChain = `-----BEGIN CERTIFICATE-----
+MIIB4zCCAWqgAwIBAgIUWYDLNZK/4shbFjW0342WaN+rg6UwCgYIKoZIzj0EAwMw
+LDEqMCgGA1UEAwwhVGVzdCBDb250ZW50IFNpZ25pbmcgSW50ZXJtZWRpYXRlMCAX
+DTIwMDEwMTAwMDAwMFoYDzIxMjAwMTAxMDAwMDAwWjAvMS0wKwYDVQQDDCRvbmVj
+cmwuY29udGVudC1zaWduYXR1cmUubW96aWxsYS5vcmcwdjAQBgcqhkjOPQIBBgUr
+gQQAIgNiAASlvRtFLGSE5Cvof83GjIL/Mq1L4+GvzqMzND099bUrAFocBwlxfwX3
+zCbabiC3yRwd+/lBN4Un7wj2rAnngZY7hQiPpOVJ6/zf36A4a8OGYmX4OZVUdLwa
+nLPFnGsynwKjSDBGMC8GA1UdEQQoMCaCJG9uZWNybC5jb250ZW50LXNpZ25hdHVy
+ZS5tb3ppbGxhLm9yZzATBgNVHSUEDDAKBggrBgEFBQcDAzAKBggqhkjOPQQDAwNn
+ADBkAjB/sPh9CUKR5rumWMdmbKWB+bi7h1Zo/++fVZ566CC2fT/DBxvAKdfXmPzO
+F6YWi+gCMHz2U9SLrMiRrlipjuCI578L6Vx1lw3AT9GxxsbdJTJlZhIE6ES//XN6
+hTLlVkTBoA==
+-----END CERTIFICATE-----
+-----BEGIN CERTIFICATE-----
+MIIBtTCCATqgAwIBAgIUTJ3V8OwGTsp/YpwOB8R/sye76MYwCgYIKoZIzj0EAwMw
+JDEiMCAGA1UEAwwZVGVzdCBDb250ZW50IFNpZ25pbmcgUm9vdDAgFw0yMDAxMDEw
+MDAwMDBaGA8yMTIwMDEwMTAwMDAwMFowLDEqMCgGA1UEAwwhVGVzdCBDb250ZW50
+IFNpZ25pbmcgSW50ZXJtZWRpYXRlMHYwEAYHKoZIzj0CAQYFK4EEACIDYgAEN9VZ
+VcthGvbLfSMz7bUQUxLh9rJRbWvdwbR7nDN9g6EAGxYvYRpoVR+4NyfnJCOy+fc6
+VJ7T4GdaoDrh0Io6NTdUi/OgItcPi2zzSrYoZF7LKK3iFoQ7XEyASgbvH4pYoyMw
+ITAPBgNVHRMBAf8EBTADAQH/MA4GA1UdDwEB/wQEAwIBBjAKBggqhkjOPQQDAwNp
+ADBmAjEAzx/KqWs62kxqugJ+TE+scmeHVPSoKcgB/5vBKozKiFQaTDFhivI8RIXX
+aWg8v6H4AjEAn9WE68xc0BZe9ZcISD14IG9m31GCGmq2m3P/FLKV0uFtCtOu3rEl
+/H8WbZMQTq1j
+-----END CERTIFICATE-----
+-----BEGIN CERTIFICATE-----
+MIIBrDCCATKgAwIBAgIUYVX70y7Ps0+QFqY8klACJaw0F80wCgYIKoZIzj0EAwMw
+JDEiMCAGA1UEAwwZVGVzdCBDb250ZW50IFNpZ25pbmcgUm9vdDAgFw0yMDAxMDEw
+MDAwMDBaGA8yMTIwMDEwMTAwMDAwMFowJDEiMCAGA1UEAwwZVGVzdCBDb250ZW50
+IFNpZ25pbmcgUm9vdDB2MBAGByqGSM49AgEGBSuBBAAiA2IABF0qkzlVprjIDDDc
+nxvtHThHmhB1TPf134rGlzY17BboDSFAwTAGF5vwL3ZDfSmI9Fu1lYCfybPw8TfP
+h05ND2cNL93DjmuO6ZuN5lET/Xt7bF/ATm21zmZ/h/2kbiWkHaMjMCEwDwYDVR0T
+AQH/BAUwAwEB/zAOBgNVHQ8BAf8EBAMCAQYwCgYIKoZIzj0EAwMDaAAwZQIwGreB
+9Ow6uEEKhsglVEiDKJ/5rk07fvEu/JY32sjWAea9gJGVQ73zyoGhde3IMvmSAjEA
+5uixbaJcOEMpZLnDzZYsY5MrPipZ0jr5J7xlnZKnw6vW1sVpE5+xIX7I9snyvUm6
+-----END CERTIFICATE-----
+`
+
+// signedServer serves a changeset of the given records, signed with the fixture signature.
+func signedServer(t *testing.T, changes string) *httptest.Server {
+	var server *httptest.Server
+	mux := http.NewServeMux()
+	mux.HandleFunc("/changeset", func(w http.ResponseWriter, r *http.Request) {
+		fmt.Fprintf(w, `{"metadata": {"signature": {"x5u": "%s/x5u", "signature": "%s"}}, "timestamp": %d, "changes": %s}`,
+			server.URL, signedSignature, signedTimestamp, changes)
+	})
+	mux.HandleFunc("/x5u", func(w http.ResponseWriter, r *http.Request) {
+		w.Write([]byte(signingChain))
+	})
+	server = httptest.NewServer(mux)
+	t.Cleanup(server.Close)
+	return server
+}
+
+func TestCanonicalPayload(t *testing.T) {
+	var records []json.RawMessage
+	if err := json.Unmarshal([]byte(signedChanges), &records); err != nil {
+		t.Fatal(err)
+	}
+	got, err := canonicalPayload(records, signedTimestamp)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if string(got) != signedPayload {
+		t.Errorf("wanted\n%s\ngot\n%s", signedPayload, got)
+	}
+}
+
+func TestCanonicalString(t *testing.T) {
+	for s, want := range map[string]string{
+		"~\x7f":   `"~\u007f"`,
+		"\x1f \"": `"\u001f \""`,
+		"é\\":     `"\u00e9\\"`,
+	} {
+		buf := new(bytes.Buffer)
+		writeCanonicalString(buf, s)
+		if buf.String() != want {
+			t.Errorf("%q: wanted %s, got %s", s, want, buf)
+		}
+	}
+}
+
+func TestVerifySignature(t *testing.T) {
+	server := signedServer(t, signedChanges)
+	c, err := RetrieveCollection(server.URL + "/records")
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := c.VerifySignature(testVerifier); err != nil {
+		t.Errorf("wanted the signature to verify, got %s", err)
+	}
+	if err := c.VerifySignature(DefaultVerifier); err == nil {
+		t.Error("wanted the chain to be rejected when it does not terminate in the production root")
+	}
+}
+
+func TestVerifySignatureTampered(t *testing.T) {
+	server := signedServer(t, strings.Replace(signedChanges, `"AQIDBAU="`, `"AQIDBAc="`, 1))
+	c, err := RetrieveCollection(server.URL + "/records")
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := c.VerifySignature(testVerifier); err == nil {
+		t.Error("wanted the signature of a tampered collection to fail verification")
+	}
+}
+
+func TestVerifySignatureUnsigned(t *testing.T) {
+	if err := (&Collection{}).VerifySignature(testVerifier); err == nil {
+		t.Error("wanted an error for a collection without a signature")
+	}
+}
+
+func TestVerifierForEnvironment(t *testing.T) {
+	// The fixture stands in for a stage signed collection, its throwaway root in place of the stage root.
+	server := signedServer(t, signedChanges)
+	defer func(stage environment) { environments[Stage] = stage }(environments[Stage])
+	environments[Stage] = environment{Endpoint: server.URL + "/records", Verifier: testVerifier}
+	url, err := Endpoint(Stage)
+	if err != nil {
+		t.Fatal(err)
+	}
+	c, err := RetrieveCollection(url)
+	if err != nil {
+		t.Fatal(err)
+	}
+	v, ok := VerifierFor(url)
+	if !ok {
+		t.Fatalf("wanted a verifier for %s", url)
+	}
+	if err := c.VerifySignature(v); err != nil {
+		t.Errorf("wanted the stage signature to verify under the stage verifier, got %s", err)
+	}
+	if v, _ := VerifierFor(OneCRLEndpoint); c.VerifySignature(v) == nil {
+		t.Error("wanted the stage signature to be rejected under the production verifier")
+	}
+	if _, ok := VerifierFor("https://kinto.example.com/v1/buckets/b/collections/c/records"); ok {
+		t.Error("wanted no verifier for a URL outside of the known environments")
+	}
+}
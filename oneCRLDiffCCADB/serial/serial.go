@@ -0,0 +1,44 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Package serial is the single place in which the certificate serial numbers of every source
+// are decoded to their raw bytes and re-encoded in one canonical form, so that the same serial
+// number joins regardless of how its source chose to write it.
+package serial
+
+import (
+	"encoding/base64"
+	"encoding/hex"
+	"math/big"
+	"strings"
+)
+
+// Canonical encodes the raw bytes of a serial number as the decimal value of the integer that
+// they encode, so that the presence or absence of a leading DER sign byte does not matter.
+func Canonical(raw []byte) string {
+	return new(big.Int).SetBytes(raw).String()
+}
+
+// FromHex canonicalizes a hex encoded serial number, as found in the CCADB. Letters may be of
+// either case, bytes may be separated by colons or spaces, and a leading zero may be omitted.
+func FromHex(s string) (string, error) {
+	s = strings.NewReplacer(":", "", " ", "").Replace(strings.TrimSpace(s))
+	if len(s)%2 != 0 {
+		s = "0" + s
+	}
+	raw, err := hex.DecodeString(s)
+	if err != nil {
+		return "", err
+	}
+	return Canonical(raw), nil
+}
+
+// FromBase64 canonicalizes a base64 encoded serial number, as found in OneCRL.
+func FromBase64(s string) (string, error) {
+	raw, err := base64.StdEncoding.DecodeString(s)
+	if err != nil {
+		return "", err
+	}
+	return Canonical(raw), nil
+}
@@ -0,0 +1,41 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package serial
+
+import (
+	"testing"
+)
+
+func TestEncodingsAgree(t *testing.T) {
+	// Each is the serial number 0x00FF01 (65281), with and without its DER sign byte.
+	want := "65281"
+	for _, s := range []string{"00FF01", "FF01", "ff01", "00:ff:01", "F F 0 1", "0FF01"} {
+		got, err := FromHex(s)
+		if err != nil {
+			t.Fatal(err)
+		}
+		if got != want {
+			t.Errorf("hex %q: wanted %s, got %s", s, want, got)
+		}
+	}
+	for _, s := range []string{"AP8B", "/wE="} {
+		got, err := FromBase64(s)
+		if err != nil {
+			t.Fatal(err)
+		}
+		if got != want {
+			t.Errorf("base64 %q: wanted %s, got %s", s, want, got)
+		}
+	}
+}
+
+func TestInvalidEncodings(t *testing.T) {
+	if _, err := FromHex("not hex"); err == nil {
+		t.Error("expected an error for invalid hex")
+	}
+	if _, err := FromBase64("not base64!"); err == nil {
+		t.Error("expected an error for invalid base64")
+	}
+}